@@ -2,5 +2,6 @@ use criterion::criterion_main;
 
 mod y2022;
 mod y2023;
+mod y2024;
 
-criterion_main!(y2022::benches, y2023::benches);
+criterion_main!(y2022::benches, y2023::benches, y2024::benches);
@@ -78,12 +78,20 @@ fn d14(c: &mut Criterion) {
 
 fn d15(c: &mut Criterion) {
     use d15::{part2::solution, INPUT};
-    c.bench_function("y2022::d15", |b| b.iter(|| solution(black_box(INPUT), 4000000)));
+    c.bench_function("y2022::d15", |b| {
+        b.iter(|| solution(black_box(INPUT), 4000000))
+    });
 }
 
 fn d16(c: &mut Criterion) {
-    use d16::{part1::solution, INPUT};
-    c.bench_function("y2022::d16", |b| b.iter(|| solution(black_box(INPUT))));
+    use d16::{part2::solution_with, Strategy, INPUT};
+    let mut group = c.benchmark_group("y2022::d16");
+    for strategy in [Strategy::DepthFirst, Strategy::Dynamic] {
+        group.bench_function(format!("{strategy:?}"), |b| {
+            b.iter(|| solution_with(black_box(INPUT), strategy))
+        });
+    }
+    group.finish();
 }
 
 fn d17(c: &mut Criterion) {
@@ -113,7 +121,7 @@ fn d21(c: &mut Criterion) {
 
 fn d22(c: &mut Criterion) {
     use d22::{part2::solution, INPUT};
-    c.bench_function("y2022::d22", |b| b.iter(|| solution::<50>(black_box(INPUT))));
+    c.bench_function("y2022::d22", |b| b.iter(|| solution(black_box(INPUT))));
 }
 
 fn d23(c: &mut Criterion) {
@@ -0,0 +1,23 @@
+use aoc::y2024::*;
+use criterion::{black_box, criterion_group, Criterion};
+
+fn d17(c: &mut Criterion) {
+    use d17::{parse, run, Compiled, INPUT};
+    let (state, program) = parse(INPUT);
+    let compiled = Compiled::new(&program);
+    let with_reg_a = |reg_a| {
+        let mut state = state.clone();
+        state.reg_a = black_box(reg_a);
+        state
+    };
+    let mut group = c.benchmark_group("y2024::d17");
+    group.bench_function("interpreter", |b| {
+        b.iter(|| (0..1000).for_each(|a| drop(run(&mut with_reg_a(a), &program))))
+    });
+    group.bench_function("compiled", |b| {
+        b.iter(|| (0..1000).for_each(|a| drop(compiled.run(&mut with_reg_a(a)))))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, d17);
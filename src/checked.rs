@@ -0,0 +1,171 @@
+//! Arithmetic for puzzles whose numbers can get large
+//!
+//! By default these are the plain operators, which panic on overflow in debug
+//! builds and wrap around in release builds. With the `checked` feature every
+//! operation is checked and reports an [`Overflow`] instead.
+
+use crate::math::Integer;
+use std::{error::Error, fmt};
+
+/// Whether the `checked` feature is enabled
+pub const ENABLED: bool = cfg!(feature = "checked");
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operation {
+    Add,
+    Sub,
+    Mul,
+    /// Also fails for division by zero
+    Div,
+}
+
+/// Result of an operation didn't fit into its type
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Overflow(pub Operation);
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let operation = match self.0 {
+            Operation::Add => "addition",
+            Operation::Sub => "subtraction",
+            Operation::Mul => "multiplication",
+            Operation::Div => "division",
+        };
+        write!(f, "Overflow in {operation}")
+    }
+}
+
+impl Error for Overflow {}
+
+pub fn add<T: Integer>(a: T, b: T) -> Result<T, Overflow> {
+    match ENABLED {
+        true => a.checked_add(b).ok_or(Overflow(Operation::Add)),
+        false => Ok(a + b),
+    }
+}
+
+pub fn sub<T: Integer>(a: T, b: T) -> Result<T, Overflow> {
+    match ENABLED {
+        true => a.checked_sub(b).ok_or(Overflow(Operation::Sub)),
+        false => Ok(a - b),
+    }
+}
+
+pub fn mul<T: Integer>(a: T, b: T) -> Result<T, Overflow> {
+    match ENABLED {
+        true => a.checked_mul(b).ok_or(Overflow(Operation::Mul)),
+        false => Ok(a * b),
+    }
+}
+
+pub fn div<T: Integer>(a: T, b: T) -> Result<T, Overflow> {
+    match ENABLED {
+        true => a.checked_div(b).ok_or(Overflow(Operation::Div)),
+        false => Ok(a / b),
+    }
+}
+
+/// Endless pseudo-random values of every magnitude up to the limits of the
+/// type, so that results land on both sides of overflowing
+#[cfg(test)]
+pub(crate) fn generate(seed: u64) -> impl Iterator<Item = u64> {
+    // xorshift64*, which needs a non-zero state
+    let mut state = seed | 1;
+    let mut next = move || {
+        state ^= state >> 12;
+        state ^= state << 25;
+        state ^= state >> 27;
+        state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    };
+    std::iter::repeat_with(move || {
+        // Half of the values use all bits, the others a random number of them
+        let bits = next();
+        match next() % 2 {
+            0 => bits,
+            _ => bits >> (next() % 64),
+        }
+    })
+}
+
+/// Like [`generate`], with either sign
+#[cfg(test)]
+pub(crate) fn generate_signed(seed: u64) -> impl Iterator<Item = i64> {
+    generate(seed)
+        .zip(generate(seed ^ 0x5555))
+        .map(|(v, sign)| match sign % 2 {
+            0 => (v >> 1) as i64,
+            _ => -((v >> 1) as i64) - (v & 1) as i64,
+        })
+}
+
+#[cfg(test)]
+fn compare(a: i64, b: i64) {
+    type Checked = fn(i64, i64) -> Result<i64, Overflow>;
+    let (x, y) = (a as i128, b as i128);
+    let checks: [(Checked, _, _); 4] = [
+        (add, Some(x + y), Operation::Add),
+        (sub, Some(x - y), Operation::Sub),
+        (mul, Some(x * y), Operation::Mul),
+        (div, x.checked_div(y), Operation::Div),
+    ];
+    for (f, exact, operation) in checks {
+        match exact.and_then(|v| i64::try_from(v).ok()) {
+            Some(v) => assert_eq!(f(a, b), Ok(v), "{a} {operation:?} {b}"),
+            None if ENABLED => assert_eq!(f(a, b), Err(Overflow(operation))),
+            None => (),
+        }
+    }
+}
+
+#[test]
+fn limits() {
+    let edges = [
+        0,
+        1,
+        2,
+        3_037_000_499,
+        3_037_000_500,
+        i64::MAX / 2,
+        i64::MAX,
+    ];
+    let values: Vec<i64> = edges.iter().flat_map(|&v| [v, -v, v - 1]).collect();
+    for &a in &values {
+        for &b in &values {
+            compare(a, b);
+        }
+    }
+}
+
+#[test]
+fn generated() {
+    let values: Vec<i64> = generate_signed(1).take(200).collect();
+    assert!(values.iter().any(|v| v.unsigned_abs() < 1 << 16));
+    assert!(values.iter().any(|v| v.unsigned_abs() > 1 << 62));
+    for &a in &values {
+        for &b in &values {
+            compare(a, b);
+        }
+    }
+    let values: Vec<u64> = generate(2).take(200).collect();
+    for &a in &values {
+        for &b in &values {
+            let (x, y) = (a as u128, b as u128);
+            let fits = |v: u128| u64::try_from(v).ok();
+            if let Some(v) = fits(x + y) {
+                assert_eq!(add(a, b), Ok(v));
+            } else if ENABLED {
+                assert_eq!(add(a, b), Err(Overflow(Operation::Add)));
+            }
+            if let Some(v) = x.checked_sub(y) {
+                assert_eq!(sub(a, b), Ok(v as u64));
+            } else if ENABLED {
+                assert_eq!(sub(a, b), Err(Overflow(Operation::Sub)));
+            }
+            if let Some(v) = fits(x * y) {
+                assert_eq!(mul(a, b), Ok(v));
+            } else if ENABLED {
+                assert_eq!(mul(a, b), Err(Overflow(Operation::Mul)));
+            }
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! Drawing graphs from puzzles with Graphviz, using its DOT language
+
+use std::fmt::Write;
+
+/// Structures that can be drawn as a graph
+pub trait ToDot {
+    /// Part of a solution that can be highlighted in the drawing
+    type Highlight: ?Sized;
+
+    fn to_dot(&self, highlight: Option<&Self::Highlight>) -> String;
+}
+
+/// Writes a DOT graph, with highlighted nodes and edges drawn in red
+pub struct Dot {
+    out: String,
+    edge_op: &'static str,
+}
+
+const HIGHLIGHT: &str = ", color=red, fontcolor=red, penwidth=2";
+
+impl Dot {
+    /// Undirected graph
+    pub fn graph(name: &str) -> Dot {
+        Dot {
+            out: format!("graph {} {{\n", quote(name)),
+            edge_op: "--",
+        }
+    }
+
+    pub fn digraph(name: &str) -> Dot {
+        Dot {
+            out: format!("digraph {} {{\n", quote(name)),
+            edge_op: "->",
+        }
+    }
+
+    pub fn node(&mut self, id: &str, label: &str, highlight: bool) {
+        let style = if highlight { HIGHLIGHT } else { "" };
+        writeln!(
+            self.out,
+            "    {} [label={}{style}];",
+            quote(id),
+            quote(label)
+        )
+        .unwrap();
+    }
+
+    pub fn edge(&mut self, from: &str, to: &str, label: Option<&str>, highlight: bool) {
+        let mut attributes = label.map(|l| format!("label={}", quote(l)));
+        if highlight {
+            attributes = Some(attributes.unwrap_or_default() + HIGHLIGHT);
+        }
+        let attributes = attributes.map_or(String::new(), |a| {
+            format!(" [{}]", a.trim_start_matches(", "))
+        });
+        writeln!(
+            self.out,
+            "    {} {} {}{attributes};",
+            quote(from),
+            self.edge_op,
+            quote(to)
+        )
+        .unwrap();
+    }
+
+    pub fn finish(self) -> String {
+        self.out + "}\n"
+    }
+}
+
+/// Quoted DOT identifier
+fn quote(s: &str) -> String {
+    format!(
+        "\"{}\"",
+        s.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
+
+#[test]
+fn dot() {
+    let mut dot = Dot::digraph("g");
+    dot.node("a", "A\n\"1\"", true);
+    dot.node("b", "B", false);
+    dot.edge("a", "b", None, false);
+    dot.edge("a", "b", Some("x"), true);
+    dot.edge("b", "a", None, true);
+    assert_eq!(
+        dot.finish(),
+        concat!(
+            "digraph \"g\" {\n",
+            "    \"a\" [label=\"A\\n\\\"1\\\"\", color=red, fontcolor=red, penwidth=2];\n",
+            "    \"b\" [label=\"B\"];\n",
+            "    \"a\" -> \"b\";\n",
+            "    \"a\" -> \"b\" [label=\"x\", color=red, fontcolor=red, penwidth=2];\n",
+            "    \"b\" -> \"a\" [color=red, fontcolor=red, penwidth=2];\n",
+            "}\n",
+        )
+    );
+}
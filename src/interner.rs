@@ -0,0 +1,55 @@
+//! Dense indices for the names that puzzle inputs use to refer to things
+
+use std::collections::HashMap;
+
+/// Assigns every distinct name an index, counting up from 0 in order of
+/// first appearance, so names can be used to index into vectors
+#[derive(Clone, Debug, Default)]
+pub struct Interner {
+    indices: HashMap<String, usize>,
+    names: Vec<String>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index of `name`, which gets the next free one if it's new
+    pub fn intern(&mut self, name: &str) -> usize {
+        if let Some(&index) = self.indices.get(name) {
+            return index;
+        }
+        self.names.push(name.to_owned());
+        self.indices.insert(name.to_owned(), self.names.len() - 1);
+        self.names.len() - 1
+    }
+
+    pub fn get(&self, name: &str) -> Option<usize> {
+        self.indices.get(name).copied()
+    }
+
+    pub fn name(&self, index: usize) -> &str {
+        &self.names[index]
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+#[test]
+fn intern() {
+    let mut names = Interner::new();
+    assert_eq!(names.intern("AA"), 0);
+    assert_eq!(names.intern("long name"), 1);
+    assert_eq!(names.intern("AA"), 0);
+    assert_eq!(names.get("long name"), Some(1));
+    assert_eq!(names.get("BB"), None);
+    assert_eq!(names.name(1), "long name");
+    assert_eq!(names.len(), 2);
+}
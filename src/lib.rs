@@ -4,6 +4,11 @@
 #[macro_use]
 extern crate impl_ops;
 
+pub mod checked;
+pub mod dot;
+pub mod interner;
+pub mod math;
+pub mod ocr;
 pub mod y2021;
 pub mod y2022;
 pub mod y2023;
@@ -0,0 +1,225 @@
+//! Number theory that several puzzles need
+
+use std::ops;
+
+/// Primitive integers, with the checked operations that [`crate::checked`]
+/// builds on
+pub trait Integer:
+    Copy
+    + PartialEq
+    + ops::Add<Output = Self>
+    + ops::Sub<Output = Self>
+    + ops::Mul<Output = Self>
+    + ops::Div<Output = Self>
+    + ops::Rem<Output = Self>
+{
+    const ZERO: Self;
+    const ONE: Self;
+
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+    fn checked_sub(self, rhs: Self) -> Option<Self>;
+    fn checked_mul(self, rhs: Self) -> Option<Self>;
+    fn checked_div(self, rhs: Self) -> Option<Self>;
+}
+
+macro_rules! impl_integer {
+    ($($t:ty),*) => {$(
+        impl Integer for $t {
+            const ZERO: Self = 0;
+            const ONE: Self = 1;
+
+            fn checked_add(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_add(self, rhs)
+            }
+            fn checked_sub(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_sub(self, rhs)
+            }
+            fn checked_mul(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_mul(self, rhs)
+            }
+            fn checked_div(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_div(self, rhs)
+            }
+        }
+    )*};
+}
+
+impl_integer!(i32, i64, u64, usize, i128, u128);
+
+/// Integers with negative values, which Bézout coefficients need
+pub trait Signed: Integer + ops::Neg<Output = Self> {}
+
+impl Signed for i32 {}
+impl Signed for i64 {}
+impl Signed for i128 {}
+
+/// Greatest common divisor of non-negative numbers
+pub fn gcd<T: Integer>(mut a: T, mut b: T) -> T {
+    while b != T::ZERO {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Least common multiple of non-negative numbers
+pub fn lcm<T: Integer>(a: T, b: T) -> T {
+    if a == T::ZERO {
+        return T::ZERO;
+    }
+    a / gcd(a, b) * b
+}
+
+/// `(g, x, y)` with `g = gcd(a, b)` and `a * x + b * y = g`
+pub fn extended_gcd<T: Signed>(a: T, b: T) -> (T, T, T) {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_x, mut x) = (T::ONE, T::ZERO);
+    let (mut old_y, mut y) = (T::ZERO, T::ONE);
+    while r != T::ZERO {
+        let q = old_r / r;
+        (old_r, r) = (r, old_r - q * r);
+        (old_x, x) = (x, old_x - q * x);
+        (old_y, y) = (y, old_y - q * y);
+    }
+    (old_r, old_x, old_y)
+}
+
+/// `a / b` if `b` divides `a` without remainder
+pub fn exact_div<T: Integer>(a: T, b: T) -> Option<T> {
+    (b != T::ZERO && a % b == T::ZERO).then(|| a / b)
+}
+
+/// `x` in `0..m` with `a * x ≡ 1 (mod m)`, if `a` and `m` are coprime
+///
+/// Returns `None` for a modulus that isn't positive.
+pub fn mod_inverse(a: i64, m: i64) -> Option<i64> {
+    if m <= 0 {
+        return None;
+    }
+    let (g, x, _) = extended_gcd(a.rem_euclid(m) as i128, m as i128);
+    (g == 1).then(|| x.rem_euclid(m as i128) as i64)
+}
+
+/// Smallest non-negative `x` with `x ≡ r (mod m)` for every `(r, m)`, and the
+/// modulus of the combined congruence
+///
+/// Moduli don't need to be coprime. Returns `None` if a modulus isn't
+/// positive, the congruences contradict each other or the result doesn't fit.
+pub fn crt(congruences: &[(i64, i64)]) -> Option<(i64, i64)> {
+    if congruences.iter().any(|&(_, m)| m <= 0) {
+        return None;
+    }
+    let (r, m) = congruences
+        .iter()
+        .try_fold((0i128, 1i128), |(r, m), &(r2, m2)| {
+            let (r2, m2) = (r2 as i128, m2 as i128);
+            let (g, p, _) = extended_gcd(m, m2);
+            if (r2 - r) % g != 0 {
+                return None;
+            }
+            let n = m2 / g;
+            let k = (r2 - r) / g % n * p % n;
+            let l = m.checked_mul(n)?;
+            Some(((r + k * m).rem_euclid(l), l))
+        })?;
+    Some((r.try_into().ok()?, m.try_into().ok()?))
+}
+
+/// `base^exp mod m` without overflowing intermediate products
+///
+/// Panics if `m` is 0.
+pub fn mod_pow(base: u64, mut exp: u64, m: u64) -> u64 {
+    let m = m as u128;
+    let mut base = base as u128 % m;
+    let mut result = 1 % m;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = result * base % m;
+        }
+        base = base * base % m;
+        exp >>= 1;
+    }
+    result as u64
+}
+
+#[test]
+fn divisors() {
+    assert_eq!(gcd(12u64, 18), 6);
+    assert_eq!(gcd(0usize, 5), 5);
+    assert_eq!(lcm(4usize, 6), 12);
+    assert_eq!(lcm(0u64, 6), 0);
+    assert_eq!(
+        [2u64, 3, 5, 7, 11, 13, 17, 19].into_iter().fold(1, lcm),
+        9699690
+    );
+    for (a, b) in [(240i64, 46), (46, 240), (17, 5), (1, 1), (0, 3)] {
+        let (g, x, y) = extended_gcd(a, b);
+        assert_eq!(g, gcd(a, b));
+        assert_eq!(a * x + b * y, g);
+    }
+    assert_eq!(exact_div(12, 4), Some(3));
+    assert_eq!(exact_div(-12, 4), Some(-3));
+    assert_eq!(exact_div(13, 4), None);
+    assert_eq!(exact_div(13, 0), None);
+    assert_eq!(exact_div(12u64, 4), Some(3));
+    assert_eq!(exact_div(0usize, 7), Some(0));
+}
+
+#[test]
+fn modular() {
+    assert_eq!(mod_inverse(3, 11), Some(4));
+    assert_eq!(mod_inverse(-3, 11), Some(7));
+    assert_eq!(mod_inverse(6, 9), None);
+    assert_eq!(crt(&[(2, 3), (3, 5), (2, 7)]), Some((23, 105)));
+    assert_eq!(crt(&[(1, 4), (3, 6)]), Some((9, 12)));
+    assert_eq!(crt(&[(1, 4), (2, 6)]), None);
+    assert_eq!(crt(&[]), Some((0, 1)));
+    let big = (i64::MAX / 3, 1_000_000_007);
+    assert_eq!(crt(&[big, (5, 7)]).map(|(r, _)| r % 7), Some(5));
+    assert_eq!(mod_pow(2, 10, 1000), 24);
+    assert_eq!(mod_pow(7, 0, 1), 0);
+    let m = u64::MAX - 58; // largest prime below 2^64
+    assert_eq!(mod_pow(3, m - 1, m), 1);
+    assert_eq!(mod_pow(u64::MAX, 2, m), 58 * 58);
+}
+
+#[test]
+fn brute_force() {
+    for a in -30i64..=30 {
+        for b in -30i64..=30 {
+            let (g, x, y) = extended_gcd(a, b);
+            assert_eq!(g.abs(), gcd(a.unsigned_abs(), b.unsigned_abs()) as i64);
+            assert_eq!(a * x + b * y, g);
+            let (g, x, y) = extended_gcd(a as i128, b as i128);
+            assert_eq!(a as i128 * x + b as i128 * y, g);
+        }
+    }
+    for m in 1i64..=30 {
+        for a in -60i64..=60 {
+            let inverse = (0..m).find(|x| (a * x).rem_euclid(m) == 1 % m);
+            assert_eq!(mod_inverse(a, m), inverse, "{a}^-1 mod {m}");
+        }
+        assert_eq!(mod_inverse(3, -m), None);
+    }
+    for m1 in 1i64..=12 {
+        for m2 in 1i64..=12 {
+            let l = lcm(m1, m2);
+            for r1 in -m1..m1 {
+                for r2 in 0..m2 {
+                    let x = (0..l).find(|x| (x - r1) % m1 == 0 && (x - r2) % m2 == 0);
+                    assert_eq!(crt(&[(r1, m1), (r2, m2)]), x.map(|x| (x, l)));
+                }
+            }
+        }
+    }
+    assert_eq!(crt(&[(1, 3), (0, 0)]), None);
+    assert_eq!(crt(&[(1, -3)]), None);
+    for m in 1u64..=20 {
+        for base in 0u64..=20 {
+            let mut expected = 1 % m;
+            for exp in 0..=20 {
+                assert_eq!(mod_pow(base, exp, m), expected);
+                expected = expected * base % m;
+            }
+        }
+    }
+}
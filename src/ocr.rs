@@ -0,0 +1,113 @@
+//! Decode capital letters drawn with `#` in the 4x6 font that several
+//! puzzles use for their answers
+//!
+//! Glyphs are 4 columns wide and separated by one empty column.
+
+use std::fmt;
+
+const HEIGHT: usize = 6;
+const WIDTH: usize = 4;
+const PITCH: usize = WIDTH + 1;
+
+#[rustfmt::skip]
+const FONT: [(char, [&str; HEIGHT]); 17] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum OcrError {
+    /// The picture doesn't have exactly six rows
+    Height(usize),
+    /// The glyph at `index` (starting at column `index * 5`) isn't part of the font
+    UnknownGlyph { index: usize, glyph: String },
+}
+
+impl fmt::Display for OcrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OcrError::Height(h) => write!(f, "Expected {HEIGHT} rows, got {h}"),
+            OcrError::UnknownGlyph { index, glyph } => write!(
+                f,
+                "Unrecognised glyph {index} at column {}:\n{glyph}",
+                index * PITCH
+            ),
+        }
+    }
+}
+
+impl std::error::Error for OcrError {}
+
+/// Read the letters of a picture where `#` is a lit pixel and anything else
+/// is dark
+pub fn recognize(picture: &str) -> Result<String, OcrError> {
+    let rows: Vec<Vec<bool>> = picture
+        .lines()
+        .map(|l| l.chars().map(|c| c == '#').collect())
+        .collect();
+    recognize_pixels(&rows)
+}
+
+/// Same as [`recognize`] for a picture that's given as rows of pixels
+pub fn recognize_pixels(rows: &[Vec<bool>]) -> Result<String, OcrError> {
+    if rows.len() != HEIGHT {
+        return Err(OcrError::Height(rows.len()));
+    }
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+    (0..width.div_ceil(PITCH))
+        .map(|index| {
+            let glyph: Vec<String> = rows
+                .iter()
+                .map(|row| {
+                    (index * PITCH..index * PITCH + WIDTH)
+                        .map(|x| if row.get(x) == Some(&true) { '#' } else { '.' })
+                        .collect()
+                })
+                .collect();
+            FONT.iter()
+                .find(|(_, g)| g.iter().eq(glyph.iter()))
+                .map(|(c, _)| *c)
+                .ok_or_else(|| OcrError::UnknownGlyph {
+                    index,
+                    glyph: glyph.join("\n"),
+                })
+        })
+        .collect()
+}
+
+#[test]
+fn font() {
+    let picture = (0..HEIGHT)
+        .map(|y| FONT.iter().map(|(_, g)| g[y]).collect::<Vec<_>>().join("."))
+        .collect::<Vec<_>>()
+        .join("\n");
+    assert_eq!(recognize(&picture), Ok("ABCEFGHIJKLOPRSUZ".into()));
+}
+
+#[test]
+fn unknown() {
+    let picture = "#..#.#..#\n#..#.#..#\n####.#.##\n#..#.##.#\n#..#.#..#\n#..#.#..#";
+    assert_eq!(
+        recognize(picture),
+        Err(OcrError::UnknownGlyph {
+            index: 1,
+            glyph: "#..#\n#..#\n#.##\n##.#\n#..#\n#..#".into()
+        })
+    );
+    assert_eq!(recognize("####"), Err(OcrError::Height(1)));
+}
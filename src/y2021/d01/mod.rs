@@ -31,8 +31,8 @@ pub mod part2 {
     use super::*;
 
     pub fn solution(s: &str) -> usize {
-        let v:Vec<_> = s.lines().map(|l| l.parse().unwrap()).collect();
-        let mut it= v.windows(3).map(|w| w.iter().sum());
+        let v: Vec<_> = s.lines().map(|l| l.parse().unwrap()).collect();
+        let mut it = v.windows(3).map(|w| w.iter().sum());
         let mut last: i32 = it.next().unwrap();
         it.filter(move |d| {
             let result = d > &last;
@@ -1,137 +1,175 @@
-use Action::*;
-use Outcome::*;
-
-#[derive(Clone, PartialEq)]
-enum Action {
-    Rock,
-    Paper,
-    Scissors,
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Lose,
+    Draw,
+    Win,
 }
 
-#[derive(PartialEq)]
-enum Outcome {
-    Win,
-    Draw,
-    Lose,
+/// Points for the own move (by index) and for the outcome of a round
+#[derive(Clone, Debug)]
+pub struct Scoring {
+    pub moves: Vec<i32>,
+    pub lose: i32,
+    pub draw: i32,
+    pub win: i32,
 }
 
-impl Outcome {
-    fn score(&self) -> i32 {
-        match self {
-            Win => 6,
-            Draw => 3,
-            Lose => 0,
+impl Scoring {
+    /// 1, 2, 3, ... points for the moves, 0/3/6 for losing/drawing/winning
+    pub fn standard(n_moves: usize) -> Scoring {
+        Scoring {
+            moves: (1..=n_moves as i32).collect(),
+            lose: 0,
+            draw: 3,
+            win: 6,
         }
     }
-}
-
-impl TryFrom<&str> for Outcome {
-    type Error = &'static str;
 
-    fn try_from(value: &str) -> Result<Self, Self::Error> {
-        match value {
-            "X" => Ok(Lose),
-            "Y" => Ok(Draw),
-            "Z" => Ok(Win),
-            _ => Err("Invalid token to parse outcome"),
+    fn outcome(&self, outcome: Outcome) -> i32 {
+        match outcome {
+            Outcome::Lose => self.lose,
+            Outcome::Draw => self.draw,
+            Outcome::Win => self.win,
         }
     }
 }
 
-impl TryFrom<&str> for Action {
-    type Error = &'static str;
+/// How the second column of a strategy guide is read
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interpretation {
+    /// The move to play
+    Move,
+    /// Whether to lose, draw or win
+    Outcome,
+}
 
-    fn try_from(value: &str) -> Result<Self, Self::Error> {
-        match value {
-            "A" | "X" => Ok(Rock),
-            "B" | "Y" => Ok(Paper),
-            "C" | "Z" => Ok(Scissors),
-            _ => Err("Invalid token to parse action"),
-        }
-    }
+/// Hand game with an odd number of moves in a cycle, like rock paper scissors
+/// or rock paper scissors Spock lizard
+///
+/// Move `a` beats move `b` if `a - b` is odd (modulo the number of moves), so
+/// every move beats half of the others. Moves are written as consecutive
+/// letters, starting at `A` for the opponent and at `X` for oneself.
+pub struct Game {
+    n_moves: usize,
+    scoring: Scoring,
+    symbols: (u8, u8),
 }
 
-impl Action {
-    fn score(&self) -> i32 {
-        match self {
-            Rock => 1,
-            Paper => 2,
-            Scissors => 3,
+impl Game {
+    pub fn new(n_moves: usize, scoring: Scoring) -> Game {
+        assert!(n_moves % 2 == 1, "Need an odd number of moves");
+        assert_eq!(scoring.moves.len(), n_moves, "Need a score for every move");
+        Game {
+            n_moves,
+            scoring,
+            symbols: (b'A', b'X'),
         }
     }
-}
 
-struct Match {
-    own: Action,
-    other: Action,
-    outcome: Outcome,
-}
+    pub fn rock_paper_scissors() -> Game {
+        Game::new(3, Scoring::standard(3))
+    }
+
+    /// Letters for the first move of the opponent's and the own column
+    pub fn with_symbols(mut self, opponent: char, own: char) -> Game {
+        self.symbols = (opponent as u8, own as u8);
+        self
+    }
 
-const GAME_LOGIC: [Match; 6] = [
-    Match {
-        own: Rock,
-        other: Paper,
-        outcome: Lose,
-    },
-    Match {
-        own: Rock,
-        other: Scissors,
-        outcome: Win,
-    },
-    Match {
-        own: Paper,
-        other: Rock,
-        outcome: Win,
-    },
-    Match {
-        own: Paper,
-        other: Scissors,
-        outcome: Lose,
-    },
-    Match {
-        own: Scissors,
-        other: Rock,
-        outcome: Lose,
-    },
-    Match {
-        own: Scissors,
-        other: Paper,
-        outcome: Win,
-    },
-];
-impl Match {
-    fn new(other: Action, own: Action) -> Self {
-        if own == other {
-            Match {
-                own,
-                other,
-                outcome: Draw,
-            }
-        } else {
-            GAME_LOGIC
-                .into_iter()
-                .find(|m| m.own == own && m.other == other)
-                .unwrap()
+    pub fn outcome(&self, own: usize, other: usize) -> Outcome {
+        match (own + self.n_moves - other) % self.n_moves {
+            0 => Outcome::Draw,
+            d if d % 2 == 1 => Outcome::Win,
+            _ => Outcome::Lose,
         }
     }
-    fn from_other_action(other: Action, outcome: Outcome) -> Self {
-        if matches!(outcome, Draw) {
-            Match {
-                own: other.clone(),
-                other,
-                outcome,
-            }
-        } else {
-            GAME_LOGIC
-                .into_iter()
-                .find(|m| m.other == other && m.outcome == outcome)
-                .unwrap()
+
+    pub fn score(&self, own: usize, other: usize) -> i32 {
+        self.scoring.moves[own] + self.scoring.outcome(self.outcome(own, other))
+    }
+
+    /// Highest scoring own move against `other`, optionally with a given outcome
+    fn best_move(&self, other: usize, outcome: Option<Outcome>) -> usize {
+        (0..self.n_moves)
+            .filter(|&own| outcome.is_none_or(|o| self.outcome(own, other) == o))
+            .max_by_key(|&own| self.score(own, other))
+            .unwrap()
+    }
+
+    fn symbol(token: &str, base: u8, n: usize) -> Result<usize, &'static str> {
+        match token.as_bytes() {
+            &[c] if c >= base && ((c - base) as usize) < n => Ok((c - base) as usize),
+            _ => Err("Invalid symbol in strategy guide"),
         }
     }
 
-    fn get_score(&self) -> i32 {
-        self.outcome.score() + self.own.score()
+    /// Total score when following the strategy guide `s`
+    ///
+    /// For [`Interpretation::Outcome`] the own column only uses three letters
+    /// for losing, drawing and winning. If several moves give that outcome,
+    /// the highest scoring one is played.
+    pub fn play(&self, s: &str, interpretation: Interpretation) -> Result<i32, &'static str> {
+        s.lines()
+            .map(|line| {
+                let (other, own) = line.split_once(' ').ok_or("Expected two columns")?;
+                let other = Game::symbol(other, self.symbols.0, self.n_moves)?;
+                let own = match interpretation {
+                    Interpretation::Move => Game::symbol(own, self.symbols.1, self.n_moves)?,
+                    Interpretation::Outcome => {
+                        let outcome = [Outcome::Lose, Outcome::Draw, Outcome::Win]
+                            [Game::symbol(own, self.symbols.1, 3)?];
+                        self.best_move(other, Some(outcome))
+                    }
+                };
+                Ok(self.score(own, other))
+            })
+            .sum()
     }
+
+    /// Strategy guide with the highest scoring move against every line of the
+    /// opponent's column
+    pub fn optimal_guide(&self, opponent: &str) -> Result<String, &'static str> {
+        opponent
+            .lines()
+            .map(|line| {
+                let other = Game::symbol(line.trim(), self.symbols.0, self.n_moves)?;
+                let own = self.best_move(other, None);
+                Ok(format!(
+                    "{} {}\n",
+                    line.trim(),
+                    (self.symbols.1 + own as u8) as char
+                ))
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn rpsls() {
+    // Rock, Paper, Scissors, Spock, Lizard
+    let game = Game::new(5, Scoring::standard(5)).with_symbols('A', 'V');
+    assert_eq!(game.outcome(3, 0), Outcome::Win);
+    assert_eq!(game.outcome(4, 3), Outcome::Win);
+    assert_eq!(game.outcome(2, 4), Outcome::Win);
+    assert_eq!(game.outcome(1, 4), Outcome::Lose);
+    assert_eq!(game.play("A Y\nE V", Interpretation::Move), Ok(10 + 7));
+    assert_eq!(game.play("A X\nE W", Interpretation::Outcome), Ok(10 + 8));
+    assert!(game.play("A Q", Interpretation::Move).is_err());
+    assert!(game.play("A Y", Interpretation::Outcome).is_err());
+}
+
+#[test]
+fn optimal() {
+    let game = Game::rock_paper_scissors();
+    let guide = game.optimal_guide("A\nB\nC").unwrap();
+    assert_eq!(guide, "A Y\nB Z\nC X\n");
+    assert_eq!(game.play(&guide, Interpretation::Move), Ok(8 + 9 + 7));
+    let scoring = Scoring {
+        moves: vec![1, 2, 30],
+        ..Scoring::standard(3)
+    };
+    let guide = Game::new(3, scoring).optimal_guide("A\nB\nC").unwrap();
+    assert_eq!(guide, "A Z\nB Z\nC Z\n");
 }
 
 pub const SAMPLE: &str = "A Y
@@ -144,14 +182,9 @@ pub mod part2 {
     use super::*;
 
     pub fn solution(s: &str) -> i32 {
-        s.lines()
-            .map(|line| {
-                let (other, outcome) = line.split_once(' ').unwrap();
-                let other: Action = other.try_into().unwrap();
-                let outcome: Outcome = outcome.try_into().unwrap();
-                Match::from_other_action(other, outcome).get_score()
-            })
-            .sum()
+        Game::rock_paper_scissors()
+            .play(s, Interpretation::Outcome)
+            .unwrap()
     }
 
     #[test]
@@ -168,15 +201,9 @@ pub mod part1 {
     use super::*;
 
     pub fn solution(input: &'static str) -> i32 {
-        input
-            .lines()
-            .map(|line| {
-                let (other, own) = line.split_once(' ').unwrap();
-                let other: Action = other.try_into().unwrap();
-                let own: Action = own.try_into().unwrap();
-                Match::new(other, own).get_score()
-            })
-            .sum()
+        Game::rock_paper_scissors()
+            .play(input, Interpretation::Move)
+            .unwrap()
     }
 
     #[test]
@@ -1,39 +1,55 @@
+use itertools::Itertools;
+use std::fmt;
 use std::ops::{Deref, DerefMut};
 use std::str::FromStr;
 
 type Stack = Vec<u8>;
 
-struct Stacks(Vec<Stack>);
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Stacks(Vec<Stack>);
 
 impl FromStr for Stacks {
     type Err = &'static str;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let mut lines_it = s.lines().rev();
-        let header = lines_it.next().unwrap();
-
-        let parse_offsets: Vec<_> = header
-            .match_indices(char::is_numeric)
-            .map(|(idx, _)| idx)
-            .collect();
-
-        let mut stacks = vec![Stack::new(); parse_offsets.len()];
-        lines_it.for_each(|line| {
-            stacks
-                .iter_mut()
-                .zip(&parse_offsets)
-                .for_each(
-                    |(stack, parse_offset)| match line.as_bytes()[*parse_offset] {
-                        b' ' => (),
-                        v @ b'A'..=b'Z' => stack.push(v),
-                        v => panic!("Couldn't parse crate: `{v}`"),
-                    },
-                );
-        });
+        let header = lines_it.next().ok_or("Missing stack labels")?;
+
+        // Crates are drawn as `[X]` with one space in between, and labels
+        // may be wider than one character
+        let mut stacks = vec![Stack::new(); header.split_whitespace().count()];
+        for line in lines_it {
+            for (i, stack) in stacks.iter_mut().enumerate() {
+                match line.as_bytes().get(4 * i + 1) {
+                    None | Some(b' ') => (),
+                    Some(v @ b'A'..=b'Z') => stack.push(*v),
+                    Some(_) => return Err("Couldn't parse crate"),
+                }
+            }
+        }
         Ok(Stacks(stacks))
     }
 }
 
+/// Draws the stacks the same way the puzzle input does
+impl fmt::Display for Stacks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let height = self.iter().map(Vec::len).max().unwrap_or(0);
+        for level in (0..height).rev() {
+            let row = self
+                .iter()
+                .map(|stack| match stack.get(level) {
+                    Some(&c) => format!("[{}]", c as char),
+                    None => "   ".into(),
+                })
+                .join(" ");
+            writeln!(f, "{row}")?;
+        }
+        let labels = (1..=self.len()).map(|i| format!("{i:^3}")).join(" ");
+        writeln!(f, "{labels}")
+    }
+}
+
 impl Deref for Stacks {
     type Target = Vec<Stack>;
 
@@ -51,17 +67,15 @@ impl DerefMut for Stacks {
 impl Stacks {
     fn top_crates(&self) -> String {
         self.iter()
-            .map(
-                |stack| *stack.last().unwrap() as char, /*Char cast could waste memroy*/
-            )
+            .map(|stack| *stack.last().expect("Empty stack") as char)
             .collect()
     }
 }
 
-struct Operation {
-    count: usize,
-    from: usize,
-    to: usize,
+pub struct Operation {
+    pub count: usize,
+    pub from: usize,
+    pub to: usize,
 }
 
 impl FromStr for Operation {
@@ -80,6 +94,48 @@ impl FromStr for Operation {
     }
 }
 
+/// Something that can perform the rearrangement procedure
+pub trait Crane {
+    fn perform(&self, stacks: &mut Stacks, op: &Operation);
+}
+
+/// Moves one crate at a time
+pub struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+    fn perform(&self, stacks: &mut Stacks, &Operation { count, from, to }: &Operation) {
+        let split_idx = stacks[from].len() - count;
+        let crates = stacks[from].split_off(split_idx);
+        stacks[to].extend(crates.iter().rev())
+    }
+}
+
+/// Moves all crates at once
+pub struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+    fn perform(&self, stacks: &mut Stacks, &Operation { count, from, to }: &Operation) {
+        let split_idx = stacks[from].len() - count;
+        let crates = stacks[from].split_off(split_idx);
+        stacks[to].extend(crates.iter())
+    }
+}
+
+/// Moves at most `.0` crates at once
+pub struct LimitedCrane(pub usize);
+
+impl Crane for LimitedCrane {
+    fn perform(&self, stacks: &mut Stacks, op: &Operation) {
+        assert!(self.0 > 0, "A crane has to lift at least one crate");
+        let mut left = op.count;
+        while left > 0 {
+            let count = left.min(self.0);
+            CrateMover9001.perform(stacks, &Operation { count, ..*op });
+            left -= count;
+        }
+    }
+}
+
 fn parse_input(input: &str) -> (Stacks, impl Iterator<Item = Operation> + '_) {
     let (stacks, procedure) = input.split_once("\n\n").unwrap();
     let stacks: Stacks = stacks.parse().unwrap();
@@ -90,6 +146,64 @@ fn parse_input(input: &str) -> (Stacks, impl Iterator<Item = Operation> + '_) {
     (stacks, procedure)
 }
 
+fn rearrange(s: &str, crane: &impl Crane) -> Stacks {
+    let (mut stacks, procedure) = parse_input(s);
+    procedure.for_each(|op| crane.perform(&mut stacks, &op));
+    stacks
+}
+
+/// The initial stacks followed by the state after every step of the procedure
+pub fn states<'a>(s: &'a str, crane: &'a impl Crane) -> impl Iterator<Item = Stacks> + 'a {
+    let (stacks, procedure) = parse_input(s);
+    std::iter::once(stacks.clone()).chain(procedure.scan(stacks, move |stacks, op| {
+        crane.perform(stacks, &op);
+        Some(stacks.clone())
+    }))
+}
+
+#[test]
+fn render() {
+    let states: Vec<_> = states(SAMPLE, &CrateMover9000).collect();
+    assert_eq!(states.len(), 5);
+    assert_eq!(
+        states[0].to_string(),
+        SAMPLE.split_once("\n\n").unwrap().0.to_owned() + "\n"
+    );
+    assert_eq!(
+        states[2].to_string(),
+        concat!(
+            "        [Z]\n",
+            "        [N]\n",
+            "    [C] [D]\n",
+            "    [M] [P]\n",
+            " 1   2   3 \n",
+        )
+    );
+}
+
+#[test]
+fn many_stacks() {
+    let stacks = Stacks(
+        (b'A'..=b'L')
+            .map(|c| vec![c; (c % 3 + 1) as usize])
+            .collect(),
+    );
+    let drawing = stacks.to_string();
+    assert!(drawing.ends_with(" 9  10  11  12 \n"));
+    assert_eq!(drawing.parse::<Stacks>(), Ok(stacks));
+}
+
+#[test]
+fn limited_crane() {
+    let s = "[A]    \n[B]    \n[C]    \n 1   2 \n\nmove 3 from 1 to 2";
+    assert_eq!(
+        rearrange(s, &LimitedCrane(2)).to_string(),
+        "    [C]\n    [A]\n    [B]\n 1   2 \n"
+    );
+    assert_eq!(rearrange(SAMPLE, &LimitedCrane(1)).top_crates(), "CMZ");
+    assert_eq!(rearrange(SAMPLE, &LimitedCrane(3)).top_crates(), "MCD");
+}
+
 pub const SAMPLE: &str = "    [D]    
 [N] [C]    
 [Z] [M] [P]
@@ -107,13 +221,7 @@ pub mod part1 {
     use super::*;
 
     pub fn solution(s: &str) -> String {
-        let (mut stacks, procedure) = parse_input(s);
-        for Operation { count, from, to } in procedure {
-            let split_idx = stacks[from].len() - count;
-            let crates = stacks[from].split_off(split_idx);
-            stacks[to].extend(crates.iter().rev())
-        }
-        stacks.top_crates()
+        rearrange(s, &CrateMover9000).top_crates()
     }
 
     #[test]
@@ -130,13 +238,7 @@ pub mod part2 {
     use super::*;
 
     pub fn solution(s: &str) -> String {
-        let (mut stacks, procedure) = parse_input(s);
-        for Operation { count, from, to } in procedure {
-            let split_idx = stacks[from].len() - count;
-            let crates = stacks[from].split_off(split_idx);
-            stacks[to].extend(crates.iter())
-        }
-        stacks.top_crates()
+        rearrange(s, &CrateMover9001).top_crates()
     }
 
     #[test]
@@ -1,44 +1,88 @@
-use std::collections::HashSet;
-use std::collections::VecDeque;
+use std::io::{self, BufReader, Bytes, Read};
 
-fn interleaved_chunks<const N: usize, T>(
-    it: impl Iterator<Item = T>,
-) -> impl Iterator<Item = [T; N]>
-where
-    [T; N]: for<'a> TryFrom<&'a mut [T]>,
-{
-    let mut buffer = VecDeque::new();
-    it.filter_map(move |v| {
-        if buffer.len() > N - 1 {
-            buffer.pop_front();
+/// Positions of all windows of `len` distinct bytes in a stream
+///
+/// Keeps a count per byte value of the current window and the number of
+/// distinct values in it, so every byte is processed in O(1). Positions are
+/// the number of bytes read once the window is complete.
+pub struct Markers<R: Read> {
+    bytes: Bytes<BufReader<R>>,
+    window: Vec<u8>,
+    counts: [usize; 256],
+    distinct: usize,
+    pos: usize,
+}
+
+impl<R: Read> Markers<R> {
+    pub fn new(reader: R, len: usize) -> Self {
+        assert!(len > 0, "Marker length has to be positive");
+        Markers {
+            bytes: BufReader::new(reader).bytes(),
+            window: vec![0; len],
+            counts: [0; 256],
+            distinct: 0,
+            pos: 0,
         }
-        buffer.push_back(v);
-        buffer.make_contiguous().try_into().ok()
-    })
+    }
 }
 
-fn offset<const N: usize>(input: &str) -> usize {
-    assert_eq!(input.len(), input.bytes().len());
-    interleaved_chunks::<N, _>(input.bytes())
-        .zip(N..)
-        .find_map(|(a, i)| {
-            let mut set = HashSet::new();
-            if a.iter().all(move |v| set.insert(v)) {
-                Some(i)
-            } else {
-                None
+impl<R: Read> Iterator for Markers<R> {
+    type Item = io::Result<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let b = match self.bytes.next()? {
+                Ok(b) => b,
+                Err(e) => return Some(Err(e)),
+            };
+            let len = self.window.len();
+            let slot = &mut self.window[self.pos % len];
+            if self.pos >= len {
+                self.counts[*slot as usize] -= 1;
+                if self.counts[*slot as usize] == 0 {
+                    self.distinct -= 1;
+                }
+            }
+            *slot = b;
+            self.counts[b as usize] += 1;
+            if self.counts[b as usize] == 1 {
+                self.distinct += 1;
+            }
+            self.pos += 1;
+            if self.distinct == len {
+                return Some(Ok(self.pos));
             }
-        })
+        }
+    }
+}
+
+fn offset(input: &str, len: usize) -> usize {
+    Markers::new(input.as_bytes(), len)
+        .next()
+        .expect("No marker found")
         .unwrap()
 }
 
+#[test]
+fn all_markers() {
+    let markers = |s: &str, len| -> Vec<usize> {
+        Markers::new(s.as_bytes(), len)
+            .map(Result::unwrap)
+            .collect()
+    };
+    assert_eq!(markers("abcabc", 3), [3, 4, 5, 6]);
+    assert_eq!(markers("aabbaab", 2), [3, 5, 7]);
+    assert_eq!(markers("aaaa", 1), [1, 2, 3, 4]);
+    assert_eq!(markers("abc", 4), []);
+}
+
 pub const INPUT: &str = include_str!("input");
 
 pub mod part1 {
     use super::*;
 
     pub fn solution(s: &str) -> usize {
-        offset::<4>(s)
+        offset(s, 4)
     }
 
     #[test]
@@ -59,7 +103,7 @@ pub mod part2 {
     use super::*;
 
     pub fn solution(s: &str) -> usize {
-        offset::<14>(s)
+        offset(s, 14)
     }
 
     #[test]
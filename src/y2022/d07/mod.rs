@@ -1,3 +1,4 @@
+use crate::dot::{Dot, ToDot};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
@@ -44,6 +45,69 @@ fn also_count_subdirectories(sizes: &mut FileSizes) {
         });
 }
 
+/// Smallest directory that frees up enough space for the update when deleted
+fn to_delete(sizes: &FileSizes) -> (&Path, usize) {
+    let required_free_up = sizes[Path::new("/")] - 40_000_000;
+    sizes
+        .iter()
+        .filter(|(_, s)| **s >= required_free_up)
+        .map(|(dir, s)| (dir.as_path(), *s))
+        .min_by_key(|(_, s)| *s)
+        .unwrap()
+}
+
+impl ToDot for FileSizes {
+    /// Directory that gets highlighted along with its parents
+    type Highlight = Path;
+
+    fn to_dot(&self, target: Option<&Path>) -> String {
+        let on_path = |dir: &Path| target.is_some_and(|t| t.starts_with(dir));
+        let mut dirs: Vec<_> = self.iter().collect();
+        dirs.sort_unstable();
+        let mut dot = Dot::digraph("directories");
+        for (dir, size) in &dirs {
+            let name = dir.file_name().map_or("/".into(), |n| n.to_string_lossy());
+            let id = dir.to_string_lossy();
+            dot.node(&id, &format!("{name}\n{size}"), on_path(dir));
+        }
+        for (dir, _) in &dirs {
+            if let Some(parent) = dir.parent() {
+                let highlight = on_path(dir);
+                let (from, to) = (parent.to_string_lossy(), dir.to_string_lossy());
+                dot.edge(&from, &to, None, highlight);
+            }
+        }
+        dot.finish()
+    }
+}
+
+/// The directory tree with total sizes as DOT graph, optionally with the
+/// directory highlighted that gets deleted in part 2
+pub fn dot(s: &str, highlight: bool) -> String {
+    let mut sizes = parse_sizes_direct_content(s);
+    also_count_subdirectories(&mut sizes);
+    let target = highlight.then(|| to_delete(&sizes).0);
+    sizes.to_dot(target)
+}
+
+#[test]
+fn sample_dot() {
+    assert_eq!(
+        dot(SAMPLE, true),
+        concat!(
+            "digraph \"directories\" {\n",
+            "    \"/\" [label=\"/\\n48381165\", color=red, fontcolor=red, penwidth=2];\n",
+            "    \"/a\" [label=\"a\\n94853\"];\n",
+            "    \"/a/e\" [label=\"e\\n584\"];\n",
+            "    \"/d\" [label=\"d\\n24933642\", color=red, fontcolor=red, penwidth=2];\n",
+            "    \"/\" -> \"/a\";\n",
+            "    \"/a\" -> \"/a/e\";\n",
+            "    \"/\" -> \"/d\" [color=red, fontcolor=red, penwidth=2];\n",
+            "}\n",
+        )
+    );
+}
+
 pub const SAMPLE: &str = "$ cd /
 $ ls
 dir a
@@ -92,14 +156,8 @@ pub mod part2 {
     use super::*;
     pub fn solution(s: &str) -> usize {
         let mut sizes = parse_sizes_direct_content(s);
-        let sizes_total: usize = sizes.values().sum();
-        let required_free_up = sizes_total - 40_000_000;
         also_count_subdirectories(&mut sizes);
-        sizes
-            .into_values()
-            .filter(|s| *s >= required_free_up)
-            .min()
-            .unwrap()
+        to_delete(&sizes).1
     }
 
     #[test]
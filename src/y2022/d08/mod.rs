@@ -25,106 +25,99 @@ impl FromStr for Grid<u8> {
     }
 }
 
-enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
+impl<T> Grid<T> {
+    fn map<U>(&self, f: impl FnMut(&T) -> U) -> Grid<U> {
+        Grid {
+            values: self.values.iter().map(f).collect(),
+            n_cols: self.n_cols,
+            n_rows: self.n_rows,
+        }
+    }
+    /// Indices of every row and column, in both directions
+    fn lines(&self) -> impl Iterator<Item = Vec<usize>> + '_ {
+        let rows =
+            (0..self.n_rows).map(|r| (0..self.n_cols).map(|c| r * self.n_cols + c).collect());
+        let cols =
+            (0..self.n_cols).map(|c| (0..self.n_rows).map(|r| r * self.n_cols + c).collect());
+        rows.chain(cols).flat_map(|line: Vec<usize>| {
+            let rev = line.iter().rev().cloned().collect();
+            [line, rev]
+        })
+    }
 }
 
-struct GridIterator<'a, T> {
-    grid: &'a Grid<T>,
-    col: usize,
-    row: usize,
-    dir: Direction,
+/// Whether each tree is visible from outside and its scenic score
+struct Survey {
+    visible: Grid<bool>,
+    scores: Grid<usize>,
 }
 
-impl<'a, T> Iterator for GridIterator<'a, T> {
-    type Item = &'a T;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        match self.dir {
-            Direction::Up => {
-                if self.row == 0 {
-                    return None;
+impl Grid<u8> {
+    /// Sweep every line in both directions with a stack of the trees that
+    /// aren't hidden behind a later, at least as tall tree yet, which takes
+    /// O(n²) for an n x n forest
+    fn survey(&self) -> Survey {
+        let mut visible = self.map(|_| false);
+        let mut scores = self.map(|_| 1);
+        let mut stack = Vec::new();
+        for line in self.lines() {
+            stack.clear();
+            for (pos, &idx) in line.iter().enumerate() {
+                let h = self.values[idx];
+                while stack.last().is_some_and(|&(_, o)| o < h) {
+                    stack.pop();
                 }
-                self.row -= 1;
-            }
-            Direction::Down => {
-                if self.row == self.grid.n_rows - 1 {
-                    return None;
-                }
-                self.row += 1;
-            }
-            Direction::Left => {
-                if self.col == 0 {
-                    return None;
-                }
-                self.col -= 1;
-            }
-            Direction::Right => {
-                if self.col == self.grid.n_cols - 1 {
-                    return None;
-                }
-                self.col += 1;
+                // Distance to the first tree that blocks the view, or to the edge
+                let view = match stack.last() {
+                    Some(&(blocker, _)) => pos - blocker,
+                    None => {
+                        visible.values[idx] = true;
+                        pos
+                    }
+                };
+                scores.values[idx] *= view;
+                stack.push((pos, h));
             }
         }
-        Some(self.grid.at((self.row, self.col)))
-    }
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        let l = match self.dir {
-            Direction::Up => self.row,
-            Direction::Down => self.grid.n_rows - self.row - 1,
-            Direction::Left => self.col,
-            Direction::Right => self.grid.n_cols - self.col - 1,
-        };
-        (l, Some(l))
+        Survey { visible, scores }
     }
 }
 
-impl<T> ExactSizeIterator for GridIterator<'_, T> {}
+#[test]
+fn survey() {
+    let Survey { visible, scores } = SAMPLE.parse::<Grid<u8>>().unwrap().survey();
+    let idx = |row: usize, col: usize| row * 5 + col;
+    assert!(!visible.values[idx(1, 3)]);
+    assert!(visible.values[idx(2, 3)]);
+    assert_eq!(scores.values[idx(1, 2)], 4);
+    assert_eq!(scores.values[idx(3, 2)], 8);
+    assert_eq!(scores.values[idx(0, 2)], 0);
+}
 
-impl<T> Grid<T> {
-    fn row_col(&self, idx: usize) -> (usize, usize) {
-        (idx / self.n_cols, idx % self.n_cols)
-    }
-    fn iter_row_col_val(&self) -> impl Iterator<Item = ((usize, usize), &T)> + '_ {
-        self.values
-            .iter()
-            .enumerate()
-            .map(|(i, v)| (self.row_col(i), v))
-    }
-    fn at(&self, (row, col): (usize, usize)) -> &T {
-        &self.values[row * self.n_cols + col]
-    }
-    fn iter_from(&self, (row, col): (usize, usize), dir: Direction) -> GridIterator<'_, T> {
-        GridIterator {
-            grid: self,
-            col,
-            row,
-            dir,
-        }
-    }
-    fn cross(&self, (row, col): (usize, usize)) -> impl Iterator<Item = GridIterator<'_, T>> {
-        [
-            self.iter_from((row, col), Direction::Up),
-            self.iter_from((row, col), Direction::Down),
-            self.iter_from((row, col), Direction::Left),
-            self.iter_from((row, col), Direction::Right),
-        ]
-        .into_iter()
-    }
+const HEAT: &[u8] = b" .:-=+*#%@";
+
+/// Scenic scores on a logarithmic scale, from ` ` for 0 up to `@` for the
+/// best spot
+pub fn heat_map(s: &str) -> String {
+    let scores = s.parse::<Grid<u8>>().unwrap().survey().scores;
+    let max = (*scores.values.iter().max().unwrap() as f64).ln_1p();
+    scores
+        .values
+        .chunks(scores.n_cols)
+        .flat_map(|row| {
+            row.iter()
+                .map(|&v| {
+                    let level = (v as f64).ln_1p() / max * (HEAT.len() - 1) as f64;
+                    HEAT[level.round() as usize] as char
+                })
+                .chain(std::iter::once('\n'))
+        })
+        .collect()
 }
 
 #[test]
-fn grid() {
-    let grid: Grid<u8> = SAMPLE.parse().unwrap();
-    let mut cross = grid.cross((2, 2));
-    assert!(cross.next().unwrap().eq([5u8, 3].iter()));
-    assert!(cross.next().unwrap().eq([5u8, 3].iter()));
-    assert!(cross.next().unwrap().eq([5u8, 6].iter()));
-    assert!(cross.next().unwrap().eq([3u8, 2].iter()));
-    assert!(cross.next().is_none());
+fn heat() {
+    assert_eq!(heat_map(SAMPLE), "     \n -#- \n %-= \n -@* \n     \n");
 }
 
 pub const SAMPLE: &str = "30373
@@ -141,8 +134,11 @@ pub mod part1 {
     pub fn solution(s: &str) -> usize {
         let heights = s.parse::<Grid<u8>>().unwrap();
         heights
-            .iter_row_col_val()
-            .filter(|(row_col, h)| heights.cross(*row_col).any(|mut ray| ray.all(|o| o < h)))
+            .survey()
+            .visible
+            .values
+            .iter()
+            .filter(|v| **v)
             .count()
     }
 
@@ -160,19 +156,7 @@ pub mod part2 {
     use super::*;
     pub fn solution(s: &str) -> usize {
         let heights: Grid<u8> = s.parse().unwrap();
-        heights
-            .iter_row_col_val()
-            .map(|(row_col, h)| {
-                heights
-                    .cross(row_col)
-                    .map(|ray|
-                            // Also count the tree that stops iteration
-                            // but only if there is one
-                            ray.len().min(ray.take_while(|o| h > o).count() + 1))
-                    .product()
-            })
-            .max()
-            .unwrap()
+        heights.survey().scores.values.into_iter().max().unwrap()
     }
 
     #[test]
@@ -2,9 +2,9 @@ use std::collections::HashSet;
 use std::ops::{Add, AddAssign, Sub};
 
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
-struct Point {
-    x: i32,
-    y: i32,
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
 }
 
 impl Sub for Point {
@@ -51,7 +51,7 @@ impl Point {
 fn parse_operations(s: &str) -> impl Iterator<Item = Point> + '_ {
     s.lines().flat_map(|line| {
         let (op, count) = line.split_once(' ').unwrap();
-        std::iter::repeat(Point::from_op(op)).take(count.parse().unwrap())
+        std::iter::repeat_n(Point::from_op(op), count.parse().unwrap())
     })
 }
 
@@ -67,24 +67,117 @@ impl Point {
     }
 }
 
+/// Rope with any number of knots that keeps track of the cells visited by each knot
+pub struct Rope {
+    knots: Vec<Point>,
+    visited: Vec<HashSet<Point>>,
+}
+
+impl Rope {
+    pub fn new(n_knots: usize) -> Rope {
+        assert!(n_knots > 0, "A rope needs at least one knot");
+        let start = Point { x: 0, y: 0 };
+        Rope {
+            knots: vec![start; n_knots],
+            visited: vec![HashSet::from([start]); n_knots],
+        }
+    }
+
+    /// Move the head by `op` and let all other knots follow
+    pub fn step(&mut self, op: Point) {
+        self.knots[0] += op;
+        for i in 1..self.knots.len() {
+            let leader = self.knots[i - 1];
+            self.knots[i].follow(&leader);
+        }
+        self.knots
+            .iter()
+            .zip(self.visited.iter_mut())
+            .for_each(|(k, v)| {
+                v.insert(*k);
+            });
+    }
+
+    pub fn knots(&self) -> &[Point] {
+        &self.knots
+    }
+
+    /// Cells visited by knot `i`, where 0 is the head
+    pub fn visited(&self, i: usize) -> &HashSet<Point> {
+        &self.visited[i]
+    }
+
+    pub fn tail_visited(&self) -> &HashSet<Point> {
+        self.visited.last().unwrap()
+    }
+
+    /// Draw the knots like the puzzle text does, with `y` pointing up
+    ///
+    /// The head is `H`, the following knots are numbered and `s` marks the
+    /// start. Knots further than 9 from the head are drawn as `#`.
+    pub fn render(&self, min: Point, max: Point) -> String {
+        (min.y..=max.y)
+            .rev()
+            .map(|y| {
+                (min.x..=max.x)
+                    .map(|x| {
+                        let p = Point { x, y };
+                        match self.knots.iter().position(|k| *k == p) {
+                            Some(0) => 'H',
+                            Some(i) => char::from_digit(i as u32, 10).unwrap_or('#'),
+                            None if p == (Point { x: 0, y: 0 }) => 's',
+                            None => '.',
+                        }
+                    })
+                    .chain(std::iter::once('\n'))
+                    .collect::<String>()
+            })
+            .collect()
+    }
+}
+
+/// Simulate `s` with `n_knots` and render one frame per step
+///
+/// All frames share the bounding box of every position any knot reaches.
+pub fn animate(s: &str, n_knots: usize) -> Vec<String> {
+    let mut rope = Rope::new(n_knots);
+    let snapshots: Vec<Vec<Point>> = parse_operations(s)
+        .map(|op| {
+            rope.step(op);
+            rope.knots.clone()
+        })
+        .collect();
+    let all = rope.visited.iter().flatten();
+    let min = Point {
+        x: all.clone().map(|p| p.x).min().unwrap(),
+        y: all.clone().map(|p| p.y).min().unwrap(),
+    };
+    let max = Point {
+        x: all.clone().map(|p| p.x).max().unwrap(),
+        y: all.map(|p| p.y).max().unwrap(),
+    };
+    snapshots
+        .into_iter()
+        .map(|knots| {
+            rope.knots = knots;
+            rope.render(min, max)
+        })
+        .collect()
+}
+
+fn tail_visited(s: &str, n_knots: usize) -> usize {
+    let mut rope = Rope::new(n_knots);
+    parse_operations(s).for_each(|op| rope.step(op));
+    rope.tail_visited().len()
+}
+
 pub const INPUT: &str = include_str!("input");
 
 pub mod part1 {
     use super::*;
 
     pub fn solution(s: &str) -> usize {
-        let start = Point { x: 0, y: 0 };
-        let mut head = start;
-        let mut tail = start;
-        parse_operations(s)
-            .map(|op| {
-                head += op;
-                tail.follow(&head);
-                tail
-            })
-            .chain(std::iter::once(start))
-            .collect::<HashSet<Point>>()
-            .len()
+        tail_visited(s, 2)
     }
 
     pub const SAMPLE: &str = "R 4
@@ -104,26 +197,20 @@ R 2";
     fn actual() {
         assert_eq!(solution(INPUT), 6212);
     }
+    #[test]
+    fn frames() {
+        let frames = animate(SAMPLE, 2);
+        assert_eq!(frames.len(), 24);
+        assert_eq!(frames[0].lines().last(), Some("1H...."));
+        assert_eq!(frames[23].lines().nth(2), Some(".1H..."));
+        assert_eq!(frames[23].lines().last(), Some("s....."));
+    }
 }
 
 pub mod part2 {
     use super::*;
     pub fn solution(s: &str) -> usize {
-        let start = Point { x: 0, y: 0 };
-        let mut knots = [start; 10];
-        parse_operations(s)
-            .map(|op| {
-                *knots.first_mut().unwrap() += op;
-                let mut last_knot = *knots.first().unwrap();
-                knots.iter_mut().skip(1).for_each(|k| {
-                    k.follow(&last_knot);
-                    last_knot = *k;
-                });
-                *knots.last().unwrap() // tail
-            })
-            .chain(std::iter::once(start))
-            .collect::<HashSet<Point>>()
-            .len()
+        tail_visited(s, 10)
     }
 
     pub const SAMPLE: &str = "R 5
@@ -1,38 +1,161 @@
-use either::Either::{Left, Right};
-use std::iter::once;
-use std::mem;
+use std::str::FromStr;
 
 pub const SAMPLE: &str = include_str!("sample");
 
 pub const INPUT: &str = include_str!("input");
 
-fn register_states(s: &str) -> impl Iterator<Item = i32> + '_ {
-    let mut buff = 0;
-    s.lines()
-        .flat_map(|line| {
-            if let Some(v) = line.strip_prefix("addx ") {
-                Left(once(0).chain(once(v.parse::<i32>().unwrap())))
-            } else {
-                Right(once(0))
+pub const CRT_WIDTH: usize = 40;
+pub const CRT_HEIGHT: usize = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    Noop,
+    Addx(i32),
+}
+
+impl Instruction {
+    /// Number of cycles until the instruction is completed
+    pub const fn cycles(&self) -> usize {
+        match self {
+            Instruction::Noop => 1,
+            Instruction::Addx(_) => 2,
+        }
+    }
+}
+
+impl FromStr for Instruction {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        match line.split_once(' ') {
+            None if line == "noop" => Ok(Instruction::Noop),
+            Some(("addx", v)) => v
+                .parse()
+                .map(Instruction::Addx)
+                .map_err(|e| format!("Invalid operand `{v}`: {e}")),
+            _ => Err(format!("Unknown instruction `{line}`")),
+        }
+    }
+}
+
+/// Observable state during one clock cycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+    /// Starts at 1
+    pub number: usize,
+    /// Register value during the cycle
+    pub x: i32,
+    /// Instruction that's being executed
+    pub instruction: Instruction,
+}
+
+impl Cycle {
+    /// Column of the pixel the CRT draws in this cycle
+    pub fn beam_x(&self) -> usize {
+        (self.number - 1) % CRT_WIDTH
+    }
+
+    /// Row of the pixel the CRT draws in this cycle
+    pub fn beam_y(&self) -> usize {
+        (self.number - 1) / CRT_WIDTH % CRT_HEIGHT
+    }
+
+    pub fn sprite_visible(&self) -> bool {
+        (self.beam_x() as i32 - self.x).abs() < 2
+    }
+}
+
+/// Returned by hooks to decide whether execution goes on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    Continue,
+    Break,
+}
+
+/// Emulates the handheld's CPU cycle by cycle
+///
+/// Iterating yields the trace of all remaining cycles.
+pub struct Cpu {
+    program: Vec<Instruction>,
+    pc: usize,
+    /// Cycles already spent on the current instruction
+    elapsed: usize,
+    x: i32,
+    cycle: usize,
+}
+
+impl Cpu {
+    pub fn new(program: Vec<Instruction>) -> Cpu {
+        Cpu {
+            program,
+            pc: 0,
+            elapsed: 0,
+            x: 1,
+            cycle: 0,
+        }
+    }
+
+    /// Run until `hook` returns [`Control::Break`] or the program ends
+    ///
+    /// Returns the cycle where execution stopped at a breakpoint. Calling
+    /// `run` again resumes with the following cycle.
+    pub fn run(&mut self, mut hook: impl FnMut(&Cycle) -> Control) -> Option<Cycle> {
+        self.find(|c| hook(c) == Control::Break)
+    }
+}
+
+impl Iterator for Cpu {
+    type Item = Cycle;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let instruction = *self.program.get(self.pc)?;
+        self.cycle += 1;
+        let cycle = Cycle {
+            number: self.cycle,
+            x: self.x,
+            instruction,
+        };
+        self.elapsed += 1;
+        if self.elapsed == instruction.cycles() {
+            if let Instruction::Addx(v) = instruction {
+                self.x += v;
             }
-        })
-        .scan(1, move |x, mut v| {
-            mem::swap(&mut v, &mut buff); // Buffer to delay v
-            *x += v;
-            Some(*x)
-        })
+            self.pc += 1;
+            self.elapsed = 0;
+        }
+        Some(cycle)
+    }
+}
+
+fn parse(s: &str) -> Vec<Instruction> {
+    s.lines().map(|l| l.parse().unwrap()).collect()
+}
+
+#[test]
+fn breakpoint() {
+    let mut cpu = Cpu::new(parse(SAMPLE));
+    let stop = cpu.run(|c| match c.number {
+        20 => Control::Break,
+        _ => Control::Continue,
+    });
+    assert_eq!(stop.map(|c| c.x), Some(21));
+    assert_eq!(cpu.next().map(|c| c.number), Some(21));
+    assert_eq!(cpu.run(|_| Control::Continue), None);
+    assert_eq!(cpu.cycle, 240);
 }
 
 pub mod part1 {
     use super::*;
 
     pub fn solution(s: &str) -> i32 {
-        register_states(s)
-            .zip(1..)
-            .skip(19)
-            .step_by(40)
-            .map(|(x, i)| x * i)
-            .sum()
+        let mut sum = 0;
+        Cpu::new(parse(s)).run(|c| {
+            if c.number % 40 == 20 {
+                sum += c.number as i32 * c.x;
+            }
+            Control::Continue
+        });
+        sum
     }
     #[test]
     fn sample() {
@@ -47,22 +170,27 @@ pub mod part1 {
 pub mod part2 {
     use super::*;
 
-    const WIDTH: i32 = 40;
-
     pub fn solution(s: &str) -> String {
-        register_states(s)
-            .zip((0..WIDTH).cycle())
-            .flat_map(|(x, crt_pos)| {
-                let pixel = if (crt_pos - x).abs() < 2 { "#" } else { "." };
-                let sep = if crt_pos == WIDTH - 1 { "\n" } else { "" };
-                once(pixel).chain(once(sep))
-            })
-            .collect::<String>()
+        crate::ocr::recognize(&render(s)).unwrap()
+    }
+
+    pub fn render(s: &str) -> String {
+        let mut screen = [['.'; CRT_WIDTH]; CRT_HEIGHT];
+        Cpu::new(parse(s)).run(|c| {
+            if c.sprite_visible() {
+                screen[c.beam_y()][c.beam_x()] = '#';
+            }
+            Control::Continue
+        });
+        screen
+            .iter()
+            .flat_map(|row| row.iter().chain(&['\n']))
+            .collect()
     }
     #[test]
     fn sample() {
         assert_eq!(
-            solution(SAMPLE),
+            render(SAMPLE),
             concat!(
                 "##..##..##..##..##..##..##..##..##..##..\n",
                 "###...###...###...###...###...###...###.\n",
@@ -75,16 +203,6 @@ pub mod part2 {
     }
     #[test]
     fn actual() {
-        assert_eq!(
-            solution(INPUT),
-            concat!(
-                "####.####.###..####.#..#..##..#..#.###..\n",
-                "...#.#....#..#.#....#..#.#..#.#..#.#..#.\n",
-                "..#..###..###..###..####.#....#..#.#..#.\n",
-                ".#...#....#..#.#....#..#.#.##.#..#.###..\n",
-                "#....#....#..#.#....#..#.#..#.#..#.#....\n",
-                "####.#....###..#....#..#..###..##..#....\n",
-            )
-        );
+        assert_eq!(solution(INPUT), "ZFBFHGUP");
     }
 }
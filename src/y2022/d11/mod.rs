@@ -1,47 +1,162 @@
+use crate::checked::{self, Overflow};
+use crate::math;
 use std::collections::VecDeque;
+use std::fmt::Write;
 use std::str::FromStr;
 
 pub const SAMPLE: &str = include_str!("sample");
 
 pub const INPUT: &str = include_str!("input");
 
-#[derive(Debug)]
-enum Operation {
-    Mul(u64),
-    Add(u64),
-    Square(),
-}
-
-impl Operation {
-    fn perform(&self, v: u64) -> u64 {
-        match self {
-            Operation::Mul(o) => o * v,
-            Operation::Add(o) => o + v,
-            Operation::Square() => v * v,
+/// Right hand side of a monkey's `Operation: new = ...` line
+///
+/// Supports `old`, constants and the binary operators `+`, `-` and `*`,
+/// where `*` binds stronger than `+` and `-`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Old,
+    Const(u64),
+    Add(Box<Expr>, Box<Expr>),
+    Sub(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    /// Evaluate for the worry level `old`, reducing modulo `modulus` if given
+    ///
+    /// Only exact values can overflow, see [`crate::checked`].
+    pub fn eval(&self, old: u64, modulus: Option<u64>) -> Result<u64, Overflow> {
+        let reduce = |v: u128| match modulus {
+            Some(m) => (v % m as u128) as u64,
+            None => v as u64,
+        };
+        let (a, b) = match self {
+            Expr::Old => return Ok(reduce(old as u128)),
+            Expr::Const(c) => return Ok(reduce(*c as u128)),
+            Expr::Add(a, b) | Expr::Sub(a, b) | Expr::Mul(a, b) => {
+                (a.eval(old, modulus)?, b.eval(old, modulus)?)
+            }
+        };
+        // Reduced values are smaller than the modulus and can't overflow in u128
+        Ok(match (self, modulus) {
+            (Expr::Add(..), Some(_)) => reduce(a as u128 + b as u128),
+            (Expr::Sub(..), Some(m)) => reduce(a as u128 + m as u128 - b as u128),
+            (Expr::Mul(..), Some(_)) => reduce(a as u128 * b as u128),
+            (Expr::Add(..), None) => checked::add(a, b)?,
+            (Expr::Sub(..), None) => checked::sub(a, b)?,
+            (Expr::Mul(..), None) => checked::mul(a, b)?,
+            _ => unreachable!(),
+        })
+    }
+}
+
+fn parse_atom(token: Option<&str>) -> Result<Expr, String> {
+    match token {
+        Some("old") => Ok(Expr::Old),
+        Some(t) => t
+            .parse()
+            .map(Expr::Const)
+            .map_err(|_| format!("Unexpected token `{t}`")),
+        None => Err("Unexpected end of expression".into()),
+    }
+}
+
+fn parse_product<'a>(
+    tokens: &mut std::iter::Peekable<impl Iterator<Item = &'a str>>,
+) -> Result<Expr, String> {
+    let mut lhs = parse_atom(tokens.next())?;
+    while tokens.next_if_eq(&"*").is_some() {
+        lhs = Expr::Mul(Box::new(lhs), Box::new(parse_atom(tokens.next())?));
+    }
+    Ok(lhs)
+}
+
+impl FromStr for Expr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut tokens = s.split_whitespace().peekable();
+        let mut lhs = parse_product(&mut tokens)?;
+        while let Some(op) = tokens.next() {
+            let rhs = Box::new(parse_product(&mut tokens)?);
+            lhs = match op {
+                "+" => Expr::Add(Box::new(lhs), rhs),
+                "-" => Expr::Sub(Box::new(lhs), rhs),
+                op => return Err(format!("Unknown operator `{op}`")),
+            };
         }
+        Ok(lhs)
     }
 }
 
-impl FromStr for Operation {
-    type Err = &'static str;
+#[test]
+fn expr_precedence() {
+    let e: Expr = "old + 2 * old - 3".parse().unwrap();
+    assert_eq!(e.eval(5, None), Ok(12));
+    assert_eq!(e.eval(5, Some(7)), Ok(5));
+    assert_eq!("old - 6".parse::<Expr>().unwrap().eval(2, Some(7)), Ok(3));
+    assert!("old / 2".parse::<Expr>().is_err());
+    assert!("old *".parse::<Expr>().is_err());
+}
+
+#[test]
+fn expr_limits() {
+    let square: Expr = "old * old".parse().unwrap();
+    let double: Expr = "old + old".parse().unwrap();
+    let max_exp = if checked::ENABLED { 64 } else { 32 };
+    for e in 0..max_exp {
+        let old = 1 << e;
+        match 1u64.checked_shl(2 * e) {
+            Some(v) => assert_eq!(square.eval(old, None), Ok(v)),
+            None => assert_eq!(
+                square.eval(old, None),
+                Err(Overflow(checked::Operation::Mul))
+            ),
+        }
+        assert_eq!(
+            square.eval(old, Some(u64::MAX)),
+            Ok(((1u128 << (2 * e)) % u64::MAX as u128) as u64)
+        );
+        if checked::ENABLED {
+            let added = old.checked_mul(2).ok_or(Overflow(checked::Operation::Add));
+            assert_eq!(double.eval(old, None), added);
+        }
+    }
+}
 
-    fn from_str(line: &str) -> Result<Self, Self::Err> {
-        let mut it = line.split_whitespace().skip(4);
-        Ok(match it.next().unwrap() {
-            "*" => match it.next().unwrap() {
-                "old" => Operation::Square(),
-                o => Operation::Mul(o.parse().unwrap()),
-            },
-            "+" => Operation::Add(it.next().unwrap().parse().unwrap()),
-            op => panic!("Couldn't parse operation {op}"),
-        })
+#[test]
+fn expr_generated() {
+    use checked::Operation;
+    type Exact = fn(u128) -> Option<u128>;
+    let exprs: [(&str, Exact, _); 5] = [
+        ("old * old", |v| Some(v * v), Operation::Mul),
+        ("old + old", |v| Some(v + v), Operation::Add),
+        ("old * 19", |v| Some(v * 19), Operation::Mul),
+        ("old + 7", |v| Some(v + 7), Operation::Add),
+        ("old - 3", |v| v.checked_sub(3), Operation::Sub),
+    ];
+    let moduli = checked::generate(3).filter(|&m| m > 0);
+    for (old, m) in checked::generate(4).zip(moduli).take(2000) {
+        for (expr, exact, operation) in exprs {
+            let e: Expr = expr.parse().unwrap();
+            let exact = exact(old as u128);
+            match exact.and_then(|v| u64::try_from(v).ok()) {
+                Some(v) => assert_eq!(e.eval(old, None), Ok(v), "{expr} for {old}"),
+                None if checked::ENABLED => assert_eq!(e.eval(old, None), Err(Overflow(operation))),
+                None => (),
+            }
+            // Reduced values never overflow, and only `old - 3` can go below 0
+            let (old, m) = (old as u128, m as u128);
+            let reduced = exact.unwrap_or(old % m + m - 3 % m) % m;
+            assert_eq!(e.eval(old as u64, Some(m as u64)), Ok(reduced as u64));
+        }
     }
 }
 
 #[derive(Debug)]
 struct Monkey {
     items: VecDeque<u64>,
-    op: Operation,
+    op: Expr,
     divisor: u64,
     destinations: (usize, usize),
     n_inspected: usize,
@@ -49,7 +164,7 @@ struct Monkey {
 
 impl Monkey {
     fn destination_for(&self, item: u64) -> usize {
-        if (item % self.divisor) == 0 {
+        if item.is_multiple_of(self.divisor) {
             self.destinations.0
         } else {
             self.destinations.1
@@ -57,6 +172,18 @@ impl Monkey {
     }
 }
 
+/// One item inspected by a monkey
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Inspection {
+    pub round: usize,
+    /// Worry level before the operation
+    pub old: u64,
+    /// Worry level after the operation and relief
+    pub new: u64,
+    /// Monkey the item was thrown to
+    pub destination: usize,
+}
+
 fn next_skip_parse<'a, F: FromStr>(it: &mut impl Iterator<Item = &'a str>, n: usize) -> F
 where
     <F as FromStr>::Err: std::fmt::Debug,
@@ -83,7 +210,14 @@ fn parse_monkeys(s: &str) -> Vec<Monkey> {
                     .skip(2)
                     .map(|n| n.trim_end_matches(',').parse().unwrap())
                     .collect(),
-                op: next_skip_parse(&mut it, 0),
+                op: it
+                    .next()
+                    .unwrap()
+                    .split_once('=')
+                    .expect("Operation should contain `=`")
+                    .1
+                    .parse()
+                    .unwrap(),
                 divisor: next_skip_parse(&mut it, 3),
                 destinations: (next_skip_parse(&mut it, 5), next_skip_parse(&mut it, 5)),
                 n_inspected: 0,
@@ -92,35 +226,156 @@ fn parse_monkeys(s: &str) -> Vec<Monkey> {
         .collect()
 }
 
-fn monkey_buisness(mut monkeys: Vec<Monkey>) -> usize {
-    monkeys.sort_unstable_by_key(|m| m.n_inspected);
-    monkeys
-        .into_iter()
-        .rev()
-        .map(|m| m.n_inspected)
-        .take(2)
-        .product()
+/// Monkeys passing items around
+///
+/// With a `relief` of 1 worry levels are tracked modulo the LCM of all test
+/// divisors, which leaves every test result unchanged. Any other relief
+/// divides the worry level, so values are kept exact.
+pub struct Simulation {
+    monkeys: Vec<Monkey>,
+    relief: u64,
+    modulus: Option<u64>,
+    round: usize,
+    history: Option<Vec<Vec<Inspection>>>,
 }
 
-const CAN_BE_SQUARED: u64 = 1u64 << 32; // squaring this is safe
+impl Simulation {
+    pub fn new(s: &str, relief: u64) -> Self {
+        assert!(
+            relief > 0,
+            "Relief has to divide worry levels by at least 1"
+        );
+        let monkeys = parse_monkeys(s);
+        let modulus = (relief == 1).then(|| monkeys.iter().map(|m| m.divisor).fold(1, math::lcm));
+        Simulation {
+            monkeys,
+            relief,
+            modulus,
+            round: 0,
+            history: None,
+        }
+    }
+
+    /// Record every inspection from now on, see [`Simulation::history`]
+    pub fn with_history(mut self) -> Self {
+        self.history = Some(self.monkeys.iter().map(|_| Vec::new()).collect());
+        self
+    }
+
+    /// On an [`Overflow`] the round stops at the item that caused it, which
+    /// stays uninspected with its monkey. Everything inspected before is kept
+    /// and the round isn't counted.
+    pub fn round(&mut self) -> Result<(), Overflow> {
+        let round = self.round + 1;
+        for i in 0..self.monkeys.len() {
+            while let Some(&old) = self.monkeys[i].items.front() {
+                let m = &self.monkeys[i];
+                let new = m.op.eval(old, self.modulus)? / self.relief;
+                let destination = m.destination_for(new);
+                self.monkeys[i].items.pop_front();
+                self.monkeys[i].n_inspected += 1;
+                if let Some(history) = &mut self.history {
+                    history[i].push(Inspection {
+                        round,
+                        old,
+                        new,
+                        destination,
+                    });
+                }
+                self.monkeys[destination].items.push_back(new);
+            }
+        }
+        self.round = round;
+        Ok(())
+    }
+
+    pub fn run(mut self, rounds: usize) -> Result<Self, Overflow> {
+        for _ in 0..rounds {
+            self.round()?;
+        }
+        Ok(self)
+    }
+
+    /// Inspections of `monkey` in order, if recording was enabled
+    pub fn history(&self, monkey: usize) -> Option<&[Inspection]> {
+        self.history.as_ref().map(|h| h[monkey].as_slice())
+    }
+
+    /// Recorded history as CSV with one line per inspection, ordered by round
+    pub fn export_history(&self) -> Option<String> {
+        let history = self.history.as_ref()?;
+        let mut rows: Vec<_> = history
+            .iter()
+            .enumerate()
+            .flat_map(|(monkey, h)| h.iter().map(move |i| (i.round, monkey, i)))
+            .collect();
+        rows.sort_by_key(|&(round, monkey, _)| (round, monkey));
+        let mut out = String::from("round,monkey,old,new,destination\n");
+        for (round, monkey, i) in rows {
+            writeln!(
+                out,
+                "{round},{monkey},{},{},{}",
+                i.old, i.new, i.destination
+            )
+            .unwrap();
+        }
+        Some(out)
+    }
+
+    pub fn monkey_business(&self) -> usize {
+        let mut n: Vec<_> = self.monkeys.iter().map(|m| m.n_inspected).collect();
+        n.sort_unstable();
+        n.into_iter().rev().take(2).product()
+    }
+}
+
+#[test]
+fn history() {
+    let sim = Simulation::new(SAMPLE, 3).with_history().run(1).unwrap();
+    assert_eq!(
+        sim.history(0).unwrap(),
+        [
+            Inspection {
+                round: 1,
+                old: 79,
+                new: 500,
+                destination: 3
+            },
+            Inspection {
+                round: 1,
+                old: 98,
+                new: 620,
+                destination: 3
+            },
+        ]
+    );
+    let csv = sim.export_history().unwrap();
+    assert_eq!(csv.lines().nth(1), Some("1,0,79,500,3"));
+    assert_eq!(csv.lines().count(), 1 + 2 + 4 + 3 + 5);
+}
+
+#[test]
+fn overflow_keeps_state() {
+    if !checked::ENABLED {
+        return;
+    }
+    let mut sim = Simulation::new(SAMPLE, 2).with_history();
+    let n_items = |sim: &Simulation| sim.monkeys.iter().map(|m| m.items.len()).sum::<usize>();
+    let before = n_items(&sim);
+    let completed = (0..1000).take_while(|_| sim.round().is_ok()).count();
+    assert!(completed < 1000);
+    assert_eq!(sim.round, completed);
+    assert_eq!(n_items(&sim), before);
+    for (i, m) in sim.monkeys.iter().enumerate() {
+        assert_eq!(m.n_inspected, sim.history(i).unwrap().len());
+    }
+}
 
 pub mod part1 {
     use super::*;
 
     pub fn solution(s: &str) -> usize {
-        let mut monkeys = parse_monkeys(s);
-        (0..20).for_each(|_| {
-            (0..monkeys.len()).for_each(|i| {
-                monkeys[i].n_inspected += monkeys[i].items.len();
-                while let Some(mut item) = monkeys[i].items.pop_front() {
-                    let m = &mut monkeys[i];
-                    item = m.op.perform(item) / 3;
-                    let dst = m.destination_for(item);
-                    monkeys[dst].items.push_back(item);
-                }
-            });
-        });
-        monkey_buisness(monkeys)
+        Simulation::new(s, 3).run(20).unwrap().monkey_business()
     }
     #[test]
     fn sample() {
@@ -136,27 +391,7 @@ pub mod part2 {
     use super::*;
 
     pub fn solution(s: &str) -> usize {
-        let mut monkeys = parse_monkeys(s);
-        let product = monkeys
-            .iter()
-            .map(|m| m.divisor)
-            .reduce(std::ops::Mul::mul)
-            .unwrap();
-        (0..10000).for_each(|_| {
-            (0..monkeys.len()).for_each(|i| {
-                monkeys[i].n_inspected += monkeys[i].items.len();
-                while let Some(mut item) = monkeys[i].items.pop_front() {
-                    let m = &mut monkeys[i];
-                    if item > CAN_BE_SQUARED {
-                        item %= product;
-                    }
-                    item = m.op.perform(item);
-                    let dst = m.destination_for(item);
-                    monkeys[dst].items.push_back(item);
-                }
-            });
-        });
-        monkey_buisness(monkeys)
+        Simulation::new(s, 1).run(10000).unwrap().monkey_business()
     }
     #[test]
     fn sample() {
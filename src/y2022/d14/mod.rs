@@ -1,4 +1,5 @@
 use std::cmp::{max, min};
+use std::collections::VecDeque;
 
 pub const SAMPLE: &str = include_str!("sample");
 
@@ -22,54 +23,97 @@ fn incr_range(v0: usize, v1: usize) -> impl Iterator<Item = usize> {
     from..=to
 }
 
-fn parse(s: &str) -> World {
-    let max_y_index = iterate_shapes(s)
-        .flatten()
-        .map(|Point { y, .. }| y)
-        .max()
-        .unwrap();
-    let size_y = max_y_index + 1 + Y_MARGIN;
-    let size_x = 700;
-    let mut world = World {
-        fields: vec![Field::Free(); size_y * size_x],
-        size_y,
-        size_x,
-    };
-    iterate_shapes(s).for_each(|mut s| {
-        let mut p0 = s.next().unwrap();
-        s.for_each(|p1| {
-            if p0.x == p1.x {
-                incr_range(p0.y, p1.y).for_each(|y| world.make_solid(&Point { x: p0.x, y }))
-            } else {
-                assert_eq!(p0.y, p1.y);
-                incr_range(p0.x, p1.x).for_each(|x| world.make_solid(&Point { x, y: p0.y }))
-            }
-            p0 = p1;
-        })
-    });
-    world
+/// What limits the cave besides the scanned rock
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Floor {
+    /// Sand falling below the lowest rock is lost
+    Abyss,
+    /// Endless floor [`Y_MARGIN`] below the lowest rock
+    Infinite,
+    /// Floor like [`Floor::Infinite`], plus vertical walls directly left and
+    /// right of the outermost rock or source
+    Walls,
+}
+
+impl World {
+    pub fn new(s: &str, floor: Floor, sources: Vec<Point>) -> World {
+        assert!(!sources.is_empty(), "Need at least one sand source");
+        let points = || iterate_shapes(s).flatten().chain(sources.iter().cloned());
+        let max_y_index = points().map(|Point { y, .. }| y).max().unwrap();
+        let size_y = max_y_index + 1 + Y_MARGIN;
+        // Sand moves at most one column per row, so it stays within `size_y`
+        // columns on either side. Columns are shifted so that the leftmost
+        // of those is 0.
+        let min_x = points().map(|Point { x, .. }| x).min().unwrap();
+        let max_x = points().map(|Point { x, .. }| x).max().unwrap();
+        let shift = |p: Point| Point {
+            x: p.x + size_y - min_x,
+            y: p.y,
+        };
+        let size_x = max_x - min_x + 2 * size_y + 1;
+        let mut world = World {
+            fields: vec![Field::Free(); size_y * size_x],
+            size_y,
+            size_x,
+            floor,
+            sources: sources.into_iter().map(shift).collect(),
+        };
+        iterate_shapes(s).for_each(|s| {
+            let mut s = s.map(shift);
+            let mut p0 = s.next().unwrap();
+            s.for_each(|p1| {
+                if p0.x == p1.x {
+                    incr_range(p0.y, p1.y).for_each(|y| world.make_solid(&Point { x: p0.x, y }))
+                } else {
+                    assert_eq!(p0.y, p1.y);
+                    incr_range(p0.x, p1.x).for_each(|x| world.make_solid(&Point { x, y: p0.y }))
+                }
+                p0 = p1;
+            })
+        });
+        if floor != Floor::Abyss {
+            (0..size_x).for_each(|x| world.make_solid(&Point { x, y: size_y - 1 }));
+        }
+        if floor == Floor::Walls {
+            (0..size_y).for_each(|y| {
+                world.make_solid(&Point { x: size_y - 1, y });
+                world.make_solid(&Point {
+                    x: max_x - min_x + size_y + 1,
+                    y,
+                });
+            });
+        }
+        world
+    }
+}
+
+fn parse(s: &str, floor: Floor) -> World {
+    World::new(s, floor, vec![SAND_SOURCE])
 }
 
 #[derive(Clone, PartialEq)]
 enum Field {
-    Solid(),
+    Rock(),
+    Sand(),
     Free(),
 }
 
-#[derive(Debug)]
-struct Point {
-    x: usize,
-    y: usize,
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Point {
+    pub x: usize,
+    pub y: usize,
 }
 
-struct World {
+pub struct World {
     fields: Vec<Field>,
     size_y: usize,
     size_x: usize,
+    floor: Floor,
+    sources: Vec<Point>,
 }
 
-const SAND_SOURCE: Point = Point { x: 500, y: 0 };
-const Y_MARGIN: usize = 2;
+pub const SAND_SOURCE: Point = Point { x: 500, y: 0 };
+pub const Y_MARGIN: usize = 2;
 
 impl World {
     fn point_to_idx(&self, &Point { x, y }: &Point) -> usize {
@@ -84,7 +128,7 @@ impl World {
         &mut self.fields[idx]
     }
     fn make_solid(&mut self, p: &Point) {
-        *self.mut_at(p) = Field::Solid();
+        *self.mut_at(p) = Field::Rock();
     }
     fn is_free(&self, p: &Point) -> bool {
         *self.at(p) == Field::Free()
@@ -92,12 +136,12 @@ impl World {
 }
 
 impl World {
-    fn spawn_sand(&mut self) -> Result<(), &str> {
-        if *self.at(&SAND_SOURCE) == Field::Solid() {
+    fn spawn_sand(&mut self, source: usize) -> Result<(), &str> {
+        let Point { mut x, y: y0 } = self.sources[source].clone();
+        if !self.is_free(&self.sources[source]) {
             return Err("Source covered");
         }
-        let mut x = SAND_SOURCE.x;
-        for y in (SAND_SOURCE.y + 1)..self.size_y {
+        for y in (y0 + 1)..self.size_y {
             if self.is_free(&Point { x, y }) {
                 continue;
             } else if self.is_free(&Point { x: x - 1, y }) {
@@ -106,28 +150,185 @@ impl World {
                 x += 1;
             } else {
                 // Make last step solid
-                self.make_solid(&Point { x, y: y - 1 });
+                *self.mut_at(&Point { x, y: y - 1 }) = Field::Sand();
                 return Ok(());
             }
         }
         Err("Out of world")
     }
 
-    fn count_spawned(&mut self) -> usize {
+    /// Drop grains one at a time, taking turns between the sources
+    ///
+    /// A source is exhausted once it's covered or one of its grains fell into
+    /// the abyss. Returns the number of grains at rest when all are exhausted.
+    pub fn simulate(&mut self) -> usize {
+        let mut active: Vec<_> = (0..self.sources.len()).collect();
         let mut count = 0;
-        while self.spawn_sand().is_ok() {
-            count += 1
+        while !active.is_empty() {
+            active.retain(|&i| {
+                let ok = self.spawn_sand(i).is_ok();
+                count += ok as usize;
+                ok
+            });
         }
         count
     }
+
+    /// Same result as [`World::simulate`], but fills every cell reachable from
+    /// a source in one BFS
+    ///
+    /// Once all sources are covered, every such cell holds sand, so this is
+    /// only valid if no grain can get lost. Returns `None` for [`Floor::Abyss`].
+    pub fn fill(&mut self) -> Option<usize> {
+        if self.floor == Floor::Abyss {
+            return None;
+        }
+        let mut queue: VecDeque<_> = self.sources.iter().cloned().collect();
+        let mut count = 0;
+        while let Some(p) = queue.pop_front() {
+            if !self.is_free(&p) {
+                continue;
+            }
+            *self.mut_at(&p) = Field::Sand();
+            count += 1;
+            queue.extend(
+                [p.x - 1, p.x, p.x + 1]
+                    .map(|x| Point { x, y: p.y + 1 })
+                    .into_iter()
+                    .filter(|p| self.is_free(p)),
+            );
+        }
+        Some(count)
+    }
+
+    /// Smallest box that contains all rock, sand and sources, ignoring an
+    /// infinite floor
+    fn bounds(&self) -> (Point, Point) {
+        let occupied = (0..self.size_x)
+            .flat_map(|x| (0..self.size_y).map(move |y| Point { x, y }))
+            .filter(|p| !self.is_free(p) && (self.floor == Floor::Abyss || p.y < self.size_y - 1))
+            .chain(self.sources.iter().cloned());
+        let (mut lo, mut hi) = (self.sources[0].clone(), self.sources[0].clone());
+        occupied.for_each(|p| {
+            lo = Point {
+                x: min(lo.x, p.x),
+                y: min(lo.y, p.y),
+            };
+            hi = Point {
+                x: max(hi.x, p.x),
+                y: max(hi.y, p.y),
+            };
+        });
+        if self.floor != Floor::Abyss {
+            hi.y = self.size_y - 1;
+        }
+        (lo, hi)
+    }
+
+    fn cells(&self) -> impl Iterator<Item = impl Iterator<Item = char> + '_> + '_ {
+        let (lo, hi) = self.bounds();
+        (lo.y..=hi.y).map(move |y| {
+            (lo.x..=hi.x).map(move |x| {
+                let p = Point { x, y };
+                match self.at(&p) {
+                    Field::Rock() => '#',
+                    Field::Sand() => 'o',
+                    Field::Free() if self.sources.contains(&p) => '+',
+                    Field::Free() => '.',
+                }
+            })
+        })
+    }
+
+    /// Draw the cave like the puzzle text does
+    pub fn render(&self) -> String {
+        self.cells()
+            .flat_map(|row| row.chain(std::iter::once('\n')))
+            .collect()
+    }
+
+    /// Binary PPM image with one pixel per cell
+    pub fn to_ppm(&self) -> Vec<u8> {
+        let rows: Vec<Vec<char>> = self.cells().map(|row| row.collect()).collect();
+        let mut out = format!("P6\n{} {}\n255\n", rows[0].len(), rows.len()).into_bytes();
+        rows.iter().flatten().for_each(|c| {
+            out.extend(match c {
+                '#' => [90, 90, 90],
+                'o' => [230, 190, 90],
+                '+' => [220, 40, 40],
+                _ => [20, 20, 30],
+            })
+        });
+        out
+    }
+}
+
+#[test]
+fn render() {
+    let mut world = parse(SAMPLE, Floor::Abyss);
+    world.simulate();
+    assert_eq!(
+        world.render(),
+        "......+...
+..........
+......o...
+.....ooo..
+....#ooo##
+...o#ooo#.
+..###ooo#.
+....oooo#.
+.o.ooooo#.
+#########.
+"
+    );
+    assert!(world.to_ppm().starts_with(b"P6\n10 10\n255\n"));
+    assert_eq!(world.to_ppm().len(), 13 + 10 * 10 * 3);
+}
+
+#[test]
+fn modes() {
+    let mut world = parse(SAMPLE, Floor::Walls);
+    let simulated = parse(SAMPLE, Floor::Walls).simulate();
+    assert_eq!(world.fill(), Some(simulated));
+    assert_eq!(world.render().lines().last(), Some("############"));
+    let sources = vec![Point { x: 497, y: 0 }, Point { x: 501, y: 0 }];
+    let mut world = World::new(SAMPLE, Floor::Infinite, sources.clone());
+    let mut simulated = World::new(SAMPLE, Floor::Infinite, sources);
+    assert_eq!(world.fill(), Some(simulated.simulate()));
+    assert_eq!(world.render(), simulated.render());
+}
+
+#[test]
+fn left_edge() {
+    let sources = vec![Point { x: 0, y: 0 }, Point { x: 3, y: 0 }];
+    let rock = "0,3 -> 2,3";
+    for floor in [Floor::Abyss, Floor::Infinite, Floor::Walls] {
+        let mut world = World::new(rock, floor, sources.clone());
+        let simulated = World::new(rock, floor, sources.clone()).simulate();
+        if floor != Floor::Abyss {
+            assert_eq!(world.fill(), Some(simulated));
+        }
+    }
+    let mut world = World::new(rock, Floor::Walls, sources.clone());
+    assert_eq!(world.fill(), Some(13));
+    assert_eq!(
+        world.render(),
+        "#o..o#
+#oooo#
+#oooo#
+####o#
+#..oo#
+######
+"
+    );
 }
 
 pub mod part1 {
     use super::*;
 
     pub fn solution(s: &str) -> usize {
-        let mut world = parse(s);
-        world.count_spawned()
+        let mut world = parse(s, Floor::Abyss);
+        world.simulate()
     }
     #[test]
     fn sample() {
@@ -143,21 +344,18 @@ pub mod part2 {
     use super::*;
 
     pub fn solution(s: &str) -> usize {
-        let mut world = parse(s);
-        (0..world.size_x).for_each(|x| {
-            world.make_solid(&Point {
-                x,
-                y: world.size_y - 1,
-            })
-        });
-        world.count_spawned()
+        let mut world = parse(s, Floor::Infinite);
+        world.fill().unwrap()
     }
     #[test]
     fn sample() {
         assert_eq!(solution(SAMPLE), 93);
     }
     #[test]
-    #[ignore = "slow when unoptimized"]
+    fn simulated() {
+        assert_eq!(parse(SAMPLE, Floor::Infinite).simulate(), 93);
+    }
+    #[test]
     fn actual() {
         assert_eq!(solution(INPUT), 28145);
     }
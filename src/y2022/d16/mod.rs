@@ -1,329 +1,346 @@
-//! This solution was made ugly to experiment with certain optimizations.
-//!
+//! Both parts only need the highest flow for every set of valves that can be
+//! opened in time, which there are two strategies to find: see [`Strategy`].
 
-use itertools::Itertools;
-use std::collections::BTreeSet;
-use std::{
-    collections::HashMap,
-    fmt::Debug,
-    ops::{Deref, DerefMut},
-};
+use crate::dot::{Dot, ToDot};
+use crate::interner::Interner;
+use std::collections::{HashMap, VecDeque};
 
 pub const SAMPLE: &str = include_str!("sample");
 pub const INPUT: &str = include_str!("input");
 
-#[derive(Eq, PartialEq, Hash, Clone, Copy, Ord, PartialOrd)]
-struct Id(u16);
+/// Name of the valve where the search starts
+const START: &str = "AA";
 
-impl Id {
-    fn new(value: &str) -> Self {
-        assert_eq!(value.len(), 2);
-        Self::new_unchecked(value)
-    }
-    const fn new_unchecked(value: &str) -> Self {
-        let value = value.as_bytes();
-        Id(value[0] as u16 | (value[1] as u16) << 8)
-    }
+/// The start and all valves with a positive flow rate, with the length of the
+/// shortest path between each pair of them
+///
+/// The start has index 0, the other valves are indexed from 1 and use bit
+/// `index - 1` in sets of opened valves.
+#[derive(Debug)]
+struct ValveMap {
+    flow_rates: Vec<u32>,
+    distances: Vec<Vec<u32>>,
+    /// Index in `names` of each of these valves
+    valves: Vec<usize>,
+    names: Interner,
+    /// Direct tunnels between all valves, by their index in `names`
+    tunnels: Vec<Vec<usize>>,
 }
 
-impl From<&str> for Id {
-    fn from(value: &str) -> Self {
-        assert_eq!(value.len(), 2);
-        Id::new(value)
+impl ValveMap {
+    fn len(&self) -> usize {
+        self.flow_rates.len()
     }
 }
 
-impl Debug for Id {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let a = (self.0 & 0xFF) as u8;
-        let b = (self.0 >> 8) as u8; // & 0xFF;
-        f.write_str(std::str::from_utf8(&[a, b]).unwrap())
-    }
+fn bit(valve: usize) -> u64 {
+    1 << (valve - 1)
 }
 
-#[test]
-fn id_debug() {
-    assert_eq!(format!("{:?}", Id::new("AZ")), "AZ")
+/// Distances from `from` to all valves, `u32::MAX` if unreachable
+fn bfs(tunnels: &[Vec<usize>], from: usize) -> Vec<u32> {
+    let mut distances = vec![u32::MAX; tunnels.len()];
+    distances[from] = 0;
+    let mut queue = VecDeque::from([from]);
+    while let Some(current) = queue.pop_front() {
+        for &next in &tunnels[current] {
+            if distances[next] == u32::MAX {
+                distances[next] = distances[current] + 1;
+                queue.push_back(next);
+            }
+        }
+    }
+    distances
 }
 
-#[derive(Debug)]
-struct ValveMap(HashMap<Id, Valve>);
-
-impl FromIterator<(Id, Valve)> for ValveMap {
-    fn from_iter<T: IntoIterator<Item = (Id, Valve)>>(iter: T) -> Self {
-        Self(HashMap::from_iter(iter))
+fn parse(s: &str) -> ValveMap {
+    let mut names = Interner::new();
+    let mut flow_rates = Vec::new();
+    let mut tunnels = Vec::new();
+    for l in s.lines() {
+        let (name, l) = l
+            .strip_prefix("Valve ")
+            .unwrap()
+            .split_once(" has flow rate=")
+            .unwrap();
+        let valve = names.intern(name);
+        let (flow_rate, l) = l
+            .split_once("; tunnels lead to valves ")
+            .unwrap_or_else(|| l.split_once("; tunnel leads to valve ").unwrap());
+        let targets: Vec<_> = l.split(", ").map(|n| names.intern(n)).collect();
+        flow_rates.resize(names.len(), 0);
+        tunnels.resize(names.len(), Vec::new());
+        flow_rates[valve] = flow_rate.parse().unwrap();
+        tunnels[valve] = targets;
+    }
+    let start = names.get(START).expect("There is no start valve");
+    let valves: Vec<_> = std::iter::once(start)
+        .chain((0..names.len()).filter(|&v| v != start && flow_rates[v] > 0))
+        .collect();
+    assert!(valves.len() <= 65, "At most 64 valves may have a flow rate");
+    ValveMap {
+        flow_rates: valves.iter().map(|&v| flow_rates[v]).collect(),
+        distances: valves
+            .iter()
+            .map(|&from| {
+                let distances = bfs(&tunnels, from);
+                valves.iter().map(|&to| distances[to]).collect()
+            })
+            .collect(),
+        valves,
+        names,
+        tunnels,
     }
 }
 
-impl Deref for ValveMap {
-    type Target = HashMap<Id, Valve>;
+impl ToDot for ValveMap {
+    /// Set of opened valves
+    type Highlight = u64;
 
-    fn deref(&self) -> &Self::Target {
-        &self.0
+    fn to_dot(&self, opened: Option<&u64>) -> String {
+        let mut dot = Dot::graph("valves");
+        for (valve, name) in (0..self.names.len()).map(|v| (v, self.names.name(v))) {
+            match self.valves.iter().position(|&v| v == valve) {
+                Some(0) | None => dot.node(name, name, false),
+                Some(i) => dot.node(
+                    name,
+                    &format!("{name}\nrate={}", self.flow_rates[i]),
+                    opened.is_some_and(|o| o & bit(i) != 0),
+                ),
+            }
+        }
+        for (valve, tunnels) in self.tunnels.iter().enumerate() {
+            for &other in tunnels {
+                // Only draw one edge for tunnels that are listed for both valves
+                if valve < other || !self.tunnels[other].contains(&valve) {
+                    dot.edge(self.names.name(valve), self.names.name(other), None, false);
+                }
+            }
+        }
+        dot.finish()
     }
 }
 
-impl DerefMut for ValveMap {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
-    }
+/// The valve network as DOT graph, optionally with the valves highlighted
+/// that are opened for the best flow in part 1
+pub fn dot(s: &str, highlight: bool) -> String {
+    let valves = parse(s);
+    let opened = highlight.then(|| Flows::search(&valves, 30, Strategy::DepthFirst).best().0);
+    valves.to_dot(opened.as_ref())
 }
 
-#[derive(Clone, Debug, PartialEq)]
-struct Valve {
-    flow_rate: u32,
-    distances: HashMap<Id, u32>,
-    direct_connections: Vec<Id>,
+#[test]
+fn sample_dot() {
+    let dot = dot(SAMPLE, true);
+    assert!(dot.starts_with("graph \"valves\" {\n    \"AA\" [label=\"AA\"];\n"));
+    assert!(dot.contains("    \"BB\" [label=\"BB\\nrate=13\", color=red"));
+    assert!(dot.contains("    \"FF\" [label=\"FF\"];\n"));
+    assert_eq!(dot.matches("penwidth").count(), 6);
+    assert!(dot.contains("    \"AA\" -- \"DD\";\n"));
+    assert!(!dot.contains("    \"DD\" -- \"AA\";\n"));
+    assert_eq!(dot.matches(" -- ").count(), 10);
 }
 
-#[derive(Debug)]
-struct StepArgs {
-    start: Id,
-    current: Id,
-    distance: u32,
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Strategy {
+    /// Follow every order of opening valves recursively
+    DepthFirst,
+    /// Go back in time minute by minute and merge all routes that arrive at
+    /// the same valve with the same set of opened valves
+    Dynamic,
 }
 
-fn step(
-    StepArgs {
-        start,
-        current,
-        distance,
-    }: StepArgs,
-    valves: &mut ValveMap,
-) -> Vec<StepArgs> {
-    let ds = &mut valves.get_mut(&start).unwrap().distances;
-    if *ds.get(&current).unwrap_or(&u32::MAX) > distance {
-        ds.insert(current, distance);
-        let r = valves[&current]
-            .direct_connections
-            .iter()
-            .filter(|id| **id != start)
-            .map(|id| StepArgs {
-                start,
-                current: *id,
-                distance: distance + 1,
-            })
-            .collect();
-        r
-    } else {
-        vec![]
+/// Highest total flow for every set of opened valves that can be opened in
+/// time, keyed by the set as bitmask
+#[derive(Debug, PartialEq, Eq)]
+pub struct Flows(HashMap<u64, u32>);
+
+impl Flows {
+    pub fn new(s: &str, steps_left: u32, strategy: Strategy) -> Flows {
+        Flows::search(&parse(s), steps_left, strategy)
     }
-}
 
-fn parse(s: &str) -> ValveMap {
-    let mut valves: ValveMap = s
-        .lines()
-        .map(|l| {
-            let (id, l) = l
-                .strip_prefix("Valve ")
-                .unwrap()
-                .split_once(" has flow rate=")
-                .unwrap();
+    fn search(valves: &ValveMap, steps_left: u32, strategy: Strategy) -> Flows {
+        let mut memory = HashMap::new();
+        match strategy {
+            Strategy::DepthFirst => find_max(0, 0, steps_left, 0, valves, &mut memory),
+            Strategy::Dynamic => find_max_dynamic(steps_left, valves, &mut memory),
+        }
+        Flows(memory)
+    }
 
-            let id = Id::new(id);
-            let (flow_rate, l) = l
-                .split_once("; tunnels lead to valves ")
-                .unwrap_or_else(|| l.split_once("; tunnel leads to valve ").unwrap());
-            (
-                id,
-                Valve {
-                    flow_rate: flow_rate.parse().unwrap(),
-                    direct_connections: l.split(", ").map(Id::new).collect(),
-                    distances: HashMap::default(),
-                },
-            )
-        })
-        .collect();
-    let mut args: Vec<StepArgs> = valves
-        .iter()
-        .flat_map(
-            |(
-                id,
-                Valve {
-                    direct_connections, ..
-                },
-            )| {
-                direct_connections.iter().map(|dst| StepArgs {
-                    start: *id,
-                    current: *dst,
-                    distance: 1,
-                })
-            },
-        )
-        .collect();
-    while !args.is_empty() {
-        args = args
-            .into_iter()
-            .flat_map(|a| step(a, &mut valves))
-            .collect();
+    /// Set of opened valves with the highest flow, and that flow
+    fn best(&self) -> (u64, u32) {
+        self.0
+            .iter()
+            .map(|(&opened, &flow)| (opened, flow))
+            .max_by_key(|&(opened, flow)| (flow, std::cmp::Reverse(opened)))
+            .unwrap()
     }
-    valves.retain(|k, v| *k == START_ID || v.flow_rate > 0);
-    valves
-}
 
-const START_ID: Id = Id::new_unchecked("AA");
+    /// Best flow when opening valves alone
+    pub fn max(&self) -> u32 {
+        self.best().1
+    }
 
-struct NextArgs {
-    current: Id,
-    unvisited: Vec<Id>,
-    steps_left: u32,
-    total_flow: u32,
+    /// Best flow when a helper opens a disjoint set of valves in parallel
+    pub fn max_with_helper(&self) -> u32 {
+        self.0
+            .iter()
+            .flat_map(|(mine, my_max)| {
+                self.0
+                    .iter()
+                    .filter(move |(helpers, _)| *mine & **helpers == 0)
+                    .map(move |(_, helper_max)| my_max + helper_max)
+            })
+            .max()
+            .unwrap()
+    }
 }
 
 fn find_max(
-    NextArgs {
-        current,
-        unvisited,
-        steps_left,
-        total_flow,
-    }: NextArgs,
-    valves: &ValveMap,
-    memory: &mut HashMap<BTreeSet<usize>, u32>,
-    max_flow: &mut u32,
-) {
-    let indices: HashMap<Id, usize> = valves
-        .0
-        .keys()
-        .sorted_unstable()
-        .cloned()
-        .zip(0..)
-        .collect();
-    let ids: HashMap<usize, Id> = valves
-        .0
-        .keys()
-        .sorted_unstable()
-        .cloned()
-        .enumerate()
-        .collect();
-    let u = unvisited.into_iter().map(|k| indices[&k]).collect();
-    let v = valves
-        .0
-        .iter()
-        .sorted_unstable_by_key(|(k, _)| *k)
-        .enumerate()
-        .inspect(|(i, (k, _))| {
-            assert_eq!(*i, indices[k]);
-        })
-        .map(
-            |(
-                _,
-                (
-                    _,
-                    Valve {
-                        flow_rate,
-                        distances,
-                        ..
-                    },
-                ),
-            )| {
-                _Valve {
-                    flow_rate: *flow_rate,
-                    distances: (0..ids.len())
-                        .map(|i| *distances.get(&ids[&i]).unwrap_or(&0u32))
-                        .collect(),
-                }
-            },
-        )
-        .collect();
-    _find_max(
-        _NextArgs {
-            current: indices[&current],
-            unvisited: u,
-            steps_left,
-            total_flow,
-        },
-        &v,
-        memory,
-        max_flow,
-    );
-}
-
-#[derive(Clone, Debug)]
-struct _Valve {
-    flow_rate: u32,
-    distances: Vec<u32>,
-}
-
-struct _NextArgs {
     current: usize,
-    unvisited: Vec<usize>,
+    opened: u64,
     steps_left: u32,
     total_flow: u32,
-}
-
-fn _find_max(
-    _NextArgs {
-        current,
-        unvisited,
-        steps_left,
-        total_flow,
-    }: _NextArgs,
-    valves: &Vec<_Valve>,
-    memory: &mut HashMap<BTreeSet<usize>, u32>,
-    max_flow: &mut u32,
+    valves: &ValveMap,
+    memory: &mut HashMap<u64, u32>,
 ) {
-    let k = BTreeSet::from_iter(unvisited.clone());
-    let f = *memory.get(&k).unwrap_or(&0).max(&total_flow);
-    memory.insert(k, f);
-    unvisited.iter().enumerate().for_each(|(i, next)| {
+    let best = memory.entry(opened).or_insert(0);
+    *best = total_flow.max(*best);
+    for next in (1..valves.len()).filter(|&v| opened & bit(v) == 0) {
         let Some(steps_left) = steps_left
             .checked_sub(
-                valves[current].distances[*next] /* time to walk */
-                 + 1, /* time to open the valve */
+                valves.distances[current][next] /* time to walk */
+                    .saturating_add(1), /* time to open the valve */
             )
             .filter(|&steps_left| steps_left != 0)
         else {
-            return;
+            continue;
         };
-        let total_flow = total_flow + valves[*next].flow_rate * steps_left;
-        *max_flow = std::cmp::max(total_flow, *max_flow);
-
-        let mut unvisited = unvisited.clone();
-        unvisited.swap_remove(i);
-        _find_max(
-            _NextArgs {
-                current: *next,
-                unvisited,
-                steps_left,
-                total_flow,
-            },
+        let total_flow = total_flow + valves.flow_rates[next] * steps_left;
+        find_max(
+            next,
+            opened | bit(next),
+            steps_left,
+            total_flow,
             valves,
             memory,
-            max_flow,
         );
-    });
+    }
+}
+
+fn find_max_dynamic(steps_left: u32, valves: &ValveMap, memory: &mut HashMap<u64, u32>) {
+    // States by the time that's left: (valve, opened) -> total flow
+    let mut states = vec![HashMap::new(); steps_left as usize + 1];
+    states[steps_left as usize].insert((0, 0), 0);
+    for t in (1..=steps_left).rev() {
+        for ((current, opened), total_flow) in std::mem::take(&mut states[t as usize]) {
+            let best = memory.entry(opened).or_insert(0);
+            *best = total_flow.max(*best);
+            for next in (1..valves.len()).filter(|&v| opened & bit(v) == 0) {
+                let Some(t) = t
+                    .checked_sub(valves.distances[current][next].saturating_add(1))
+                    .filter(|&t| t != 0)
+                else {
+                    continue;
+                };
+                let total_flow = total_flow + valves.flow_rates[next] * t;
+                let stored = states[t as usize]
+                    .entry((next, opened | bit(next)))
+                    .or_insert(0);
+                *stored = total_flow.max(*stored);
+            }
+        }
+    }
+}
+
+/// Input with `n_valves` valves named `AA`, `V1`, `V2`, ... in a random
+/// tree with some extra tunnels, `n_working` of which have a flow rate
+#[cfg(test)]
+fn generate(n_valves: usize, n_working: usize, seed: u64) -> String {
+    let mut state = seed;
+    let mut random = |n: usize| {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (state >> 33) as usize % n
+    };
+    let name = |v: usize| match v {
+        0 => START.to_owned(),
+        v => format!("V{v}"),
+    };
+    let mut tunnels = vec![std::collections::BTreeSet::new(); n_valves];
+    let mut edges: Vec<_> = (1..n_valves).map(|v| (v, random(v))).collect();
+    for _ in 0..n_valves / 4 {
+        edges.push((random(n_valves), random(n_valves)));
+    }
+    for (a, b) in edges.into_iter().filter(|(a, b)| a != b) {
+        tunnels[a].insert(b);
+        tunnels[b].insert(a);
+    }
+    let mut flow_rates = vec![0; n_valves];
+    while flow_rates.iter().filter(|&&f| f > 0).count() < n_working {
+        flow_rates[1 + random(n_valves - 1)] = 1 + random(25);
+    }
+    (0..n_valves)
+        .map(|v| {
+            let targets: Vec<_> = tunnels[v].iter().map(|&t| name(t)).collect();
+            let tunnels = match targets.len() {
+                1 => "tunnel leads to valve",
+                _ => "tunnels lead to valves",
+            };
+            format!(
+                "Valve {} has flow rate={}; {tunnels} {}\n",
+                name(v),
+                flow_rates[v],
+                targets.join(", ")
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn names() {
+    // Valve names are arbitrary strings
+    let renamed = SAMPLE.replace("BB", "bypass").replace("JJ", "J");
+    assert_eq!(part1::solution(&renamed), 1651);
+    let generated = generate(300, 8, 1);
+    assert_eq!(parse(&generated).len(), 9);
+    assert!(part1::solution(&generated) > 0);
+}
+
+#[test]
+fn strategies() {
+    let generated = (0..5).map(|seed| generate(100, 10, seed));
+    for s in [SAMPLE.to_owned(), INPUT.to_owned()]
+        .into_iter()
+        .chain(generated)
+    {
+        for steps_left in [26, 30] {
+            assert_eq!(
+                Flows::new(&s, steps_left, Strategy::DepthFirst),
+                Flows::new(&s, steps_left, Strategy::Dynamic)
+            );
+        }
+    }
 }
 
 pub mod part1 {
     use super::*;
 
     pub fn solution(s: &str) -> u32 {
-        let valves = parse(s);
-        let mut total_flow = 0;
-        let mut memory = HashMap::new();
-        find_max(
-            NextArgs {
-                current: START_ID,
-                unvisited: valves
-                    .clone()
-                    .into_keys()
-                    .filter(|k| *k != START_ID)
-                    .collect(),
-                steps_left: 30,
-                total_flow: 0,
-            },
-            &valves,
-            &mut memory,
-            &mut total_flow,
-        );
-        total_flow
+        solution_with(s, Strategy::DepthFirst)
+    }
+
+    pub fn solution_with(s: &str, strategy: Strategy) -> u32 {
+        Flows::new(s, 30, strategy).max()
     }
     #[test]
     fn sample() {
         assert_eq!(solution(SAMPLE), 1651);
     }
     #[test]
-    #[ignore = "slow"]
     fn actual() {
         assert_eq!(solution(INPUT), 1880);
     }
@@ -333,57 +350,19 @@ pub mod part2 {
     use super::*;
 
     pub fn solution(s: &str) -> u32 {
-        let valves = parse(s);
-        let unvisited: Vec<Id> = valves
-            .clone()
-            .into_keys()
-            .filter(|k| *k != START_ID)
-            .collect();
-        let mut _max = 0;
-        let mut memory = HashMap::new();
-        let mut max = 0;
-        find_max(
-            NextArgs {
-                current: START_ID,
-                unvisited: unvisited.clone(),
-                steps_left: 26,
-                total_flow: 0,
-            },
-            &valves,
-            &mut memory,
-            &mut max,
-        );
-        let indices: HashMap<Id, usize> = valves
-            .0
-            .keys()
-            .sorted_unstable()
-            .cloned()
-            .zip(0..)
-            .collect();
-        let unvisited: Vec<usize> = unvisited.into_iter().map(|k| indices[&k]).collect();
-        memory
-            .iter()
-            .flat_map(|(my_unvisited, my_max)| {
-                memory.iter().filter_map(|(el_unvisited, el_max)| {
-                    if unvisited
-                        .iter()
-                        .all(|u| my_unvisited.contains(u) | el_unvisited.contains(u))
-                    {
-                        Some(*my_max + *el_max)
-                    } else {
-                        None
-                    }
-                })
-            })
-            .max()
-            .unwrap()
+        solution_with(s, Strategy::DepthFirst)
+    }
+
+    /// The elephant and I open disjoint sets of valves
+    pub fn solution_with(s: &str, strategy: Strategy) -> u32 {
+        Flows::new(s, 26, strategy).max_with_helper()
     }
     #[test]
     fn sample() {
         assert_eq!(solution(SAMPLE), 1707);
+        assert_eq!(solution_with(SAMPLE, Strategy::Dynamic), 1707);
     }
     #[test]
-    #[ignore = "slow"]
     fn actual() {
         assert_eq!(solution(INPUT), 2520);
     }
@@ -1,21 +1,48 @@
 use itertools::Itertools;
-use std::collections::HashSet;
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+use std::str::FromStr;
 
 pub const SAMPLE: &str = include_str!("sample");
 pub const INPUT: &str = include_str!("input");
 
 const N_DIMS: usize = 3;
-type Point = [i32; N_DIMS];
+pub type Point = [i32; N_DIMS];
 
-fn parse(s: &str) -> HashSet<Point> {
+/// A line that isn't three comma separated integers
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// Starting at 1
+    pub line: usize,
+    pub content: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Expected `x,y,z` in line {}: `{}`",
+            self.line, self.content
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn parse(s: &str) -> Result<Vec<Point>, ParseError> {
     s.lines()
-        .map(|l| {
+        .zip(1..)
+        .map(|(l, line)| {
             let (x, y, z) = l
                 .split(',')
-                .map(|x| x.parse().unwrap())
+                .map(|x| x.parse().ok())
                 .collect_tuple()
-                .unwrap();
-            [x, y, z]
+                .and_then(|(x, y, z)| Some((x?, y?, z?)))
+                .ok_or_else(|| ParseError {
+                    line,
+                    content: l.to_string(),
+                })?;
+            Ok([x, y, z])
         })
         .collect()
 }
@@ -30,18 +57,213 @@ fn neighbors(p: &Point) -> impl Iterator<Item = Point> + '_ {
     })
 }
 
-fn surface(ps: HashSet<Point>) -> usize {
-    ps.iter()
-        .flat_map(|p| neighbors(p).filter(|p| !ps.contains(p)))
-        .count()
+/// Dense grid of unit cubes
+///
+/// The grid has one layer of air around the bounding box of the lava, so the
+/// exterior air is always connected.
+pub struct Voxels {
+    origin: Point,
+    dims: [usize; N_DIMS],
+    cells: Vec<bool>,
+}
+
+impl FromStr for Voxels {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Voxels::new(parse(s)?))
+    }
+}
+
+impl Voxels {
+    pub fn new(lava: impl IntoIterator<Item = Point>) -> Voxels {
+        let lava = lava.into_iter().collect_vec();
+        let minmax: [(i32, i32); N_DIMS] = std::array::from_fn(|d| {
+            lava.iter()
+                .map(|p| p[d])
+                .minmax()
+                .into_option()
+                .unwrap_or((0, 0))
+        });
+        let mut voxels = Voxels {
+            origin: minmax.map(|(min, _)| min - 1),
+            dims: minmax.map(|(min, max)| (max - min + 3) as usize),
+            cells: Vec::new(),
+        };
+        voxels.cells = vec![false; voxels.dims.iter().product()];
+        for p in lava {
+            let idx = voxels.idx(&p).unwrap();
+            voxels.cells[idx] = true;
+        }
+        voxels
+    }
+
+    fn idx(&self, p: &Point) -> Option<usize> {
+        p.iter()
+            .zip(self.origin)
+            .zip(self.dims)
+            .rev()
+            .try_fold(0, |acc, ((v, o), d)| {
+                let v = usize::try_from(v - o).ok().filter(|v| *v < d)?;
+                Some(acc * d + v)
+            })
+    }
+
+    fn point(&self, mut idx: usize) -> Point {
+        std::array::from_fn(|d| {
+            let v = idx % self.dims[d];
+            idx /= self.dims[d];
+            self.origin[d] + v as i32
+        })
+    }
+
+    pub fn contains(&self, p: &Point) -> bool {
+        self.idx(p).is_some_and(|i| self.cells[i])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Point> + '_ {
+        self.cells.iter().positions(|c| *c).map(|i| self.point(i))
+    }
+
+    /// Label the 6-connected components of all cells that are `lava` or not
+    ///
+    /// Returns the label per cell (`None` for cells of the other kind) and
+    /// the number of components.
+    fn label(&self, lava: bool) -> (Vec<Option<usize>>, usize) {
+        let mut labels = vec![None; self.cells.len()];
+        let mut n = 0;
+        for start in 0..self.cells.len() {
+            if self.cells[start] != lava || labels[start].is_some() {
+                continue;
+            }
+            labels[start] = Some(n);
+            let mut queue = vec![start];
+            while let Some(i) = queue.pop() {
+                let p = self.point(i);
+                for j in neighbors(&p).filter_map(|p| self.idx(&p)) {
+                    if self.cells[j] == lava && labels[j].is_none() {
+                        labels[j] = Some(n);
+                        queue.push(j);
+                    }
+                }
+            }
+            n += 1;
+        }
+        (labels, n)
+    }
+
+    fn group(&self, (labels, n): (Vec<Option<usize>>, usize)) -> Vec<Vec<Point>> {
+        let mut groups = vec![Vec::new(); n];
+        labels
+            .iter()
+            .enumerate()
+            .for_each(|(i, l)| l.iter().for_each(|&l| groups[l].push(self.point(i))));
+        groups
+    }
+
+    /// Connected droplets of lava
+    pub fn components(&self) -> Vec<Vec<Point>> {
+        self.group(self.label(true))
+    }
+
+    /// Pockets of air that are enclosed by lava
+    pub fn cavities(&self) -> Vec<Vec<Point>> {
+        let mut cavities = self.group(self.label(false));
+        // The first cell is a corner of the padding, so air component 0 is the exterior
+        cavities.remove(0);
+        cavities
+    }
+
+    /// Faces between a lava cell and an air cell accepted by `is_open`
+    fn faces<'a>(
+        &'a self,
+        is_open: impl Fn(usize) -> bool + 'a,
+    ) -> impl Iterator<Item = (Point, Point)> + 'a {
+        self.iter().flat_map(move |p| {
+            neighbors(&p)
+                .filter(|n| self.idx(n).is_some_and(&is_open))
+                .map(move |n| (p, n))
+                .collect_vec()
+        })
+    }
+
+    fn exterior(&self) -> Vec<bool> {
+        let (labels, _) = self.label(false);
+        labels.into_iter().map(|l| l == Some(0)).collect()
+    }
+
+    pub fn surface_area(&self) -> usize {
+        self.faces(|i| !self.cells[i]).count()
+    }
+
+    /// Surface area without the faces facing a cavity
+    pub fn exterior_surface(&self) -> usize {
+        let exterior = self.exterior();
+        self.faces(|i| exterior[i]).count()
+    }
+
+    /// Wavefront OBJ mesh with one quad per lava face
+    pub fn to_obj(&self, exterior_only: bool) -> String {
+        let exterior = self.exterior();
+        let mut vertices: HashMap<Point, usize> = HashMap::new();
+        let mut out = String::new();
+        let mut quads = Vec::new();
+        for (p, n) in self.faces(|i| !exterior_only || exterior[i]) {
+            let d = (0..N_DIMS).find(|&d| p[d] != n[d]).unwrap();
+            let (a, b) = ((d + 1) % N_DIMS, (d + 2) % N_DIMS);
+            // Corners of the face, counter-clockwise when viewed from outside
+            let mut corners = [(0, 0), (1, 0), (1, 1), (0, 1)];
+            if n[d] < p[d] {
+                corners.reverse();
+            }
+            let quad = corners.map(|(da, db)| {
+                let mut v = p;
+                v[d] = p[d].max(n[d]);
+                v[a] += da;
+                v[b] += db;
+                let next = vertices.len() + 1;
+                *vertices.entry(v).or_insert_with(|| {
+                    writeln!(out, "v {} {} {}", v[0], v[1], v[2]).unwrap();
+                    next
+                })
+            });
+            quads.push(quad);
+        }
+        for [a, b, c, d] in quads {
+            writeln!(out, "f {a} {b} {c} {d}").unwrap();
+        }
+        out
+    }
+}
+
+#[test]
+fn voxels() {
+    let voxels: Voxels = SAMPLE.parse().unwrap();
+    assert_eq!(voxels.components().len(), 6);
+    assert_eq!(voxels.cavities(), vec![vec![[2, 2, 5]]]);
+    let obj = voxels.to_obj(true);
+    assert_eq!(obj.lines().filter(|l| l.starts_with('f')).count(), 58);
+
+    let two: Voxels = "1,1,1\n3,1,1".parse().unwrap();
+    for bad in ["1,1,1\n3,1", "1,1,1\n3,1,1,1", "1,1,1\n3,x,1"] {
+        let err = bad.parse::<Voxels>().err();
+        assert_eq!(err.map(|e| e.line), Some(2), "{bad}");
+    }
+    assert_eq!(two.components().len(), 2);
+    assert_eq!(
+        two.to_obj(false)
+            .lines()
+            .filter(|l| l.starts_with('v'))
+            .count(),
+        16
+    );
 }
 
 pub mod part1 {
     use super::*;
 
     pub fn solution(s: &str) -> usize {
-        let ps = parse(s);
-        surface(ps)
+        s.parse::<Voxels>().unwrap().surface_area()
     }
     #[test]
     fn sample() {
@@ -57,36 +279,7 @@ pub mod part2 {
     use super::*;
 
     pub fn solution(s: &str) -> usize {
-        let lava = parse(s);
-
-        let minmax: [(i32, i32); N_DIMS] = (0..N_DIMS)
-            .map(|d| lava.iter().map(|p| p[d]).minmax().into_option().unwrap())
-            .collect_vec()
-            .try_into()
-            .unwrap();
-
-        let mut unkown: HashSet<Point> = minmax
-            .iter()
-            .map(|(min, max)| *min..=*max)
-            .multi_cartesian_product()
-            .map(|v| v.try_into().unwrap())
-            .filter(|p| !lava.contains(p))
-            .collect();
-
-        // Remove all the _air_ form `unknown` by expanding starting from the surface
-        while let Some(p) = unkown.iter().find(|p| {
-            p.iter()
-                .zip(minmax)
-                .any(|(p, (min, max))| *p == min || *p == max)
-        }) {
-            let mut que = vec![*p];
-            while let Some(p) = que.pop() {
-                if let Some(p) = unkown.take(&p) {
-                    que.extend(neighbors(&p));
-                }
-            }
-        }
-        surface(lava) - surface(unkown)
+        s.parse::<Voxels>().unwrap().exterior_surface()
     }
     #[test]
     fn sample() {
@@ -115,7 +115,7 @@ impl<'a> State<'a> {
     }
 
     #[inline(always)]
-    fn construct(&self, r: Kind) -> Option<State> {
+    fn construct(&self, r: Kind) -> Option<State<'a>> {
         self.inventory.checked_sub(&self.costs[r]).map(|inv| State {
             costs: self.costs,
             inventory: inv + &self.n_robots,
@@ -1,3 +1,5 @@
+use crate::checked::{self, Overflow};
+use crate::dot::{Dot, ToDot};
 use itertools::Itertools;
 use std::collections::HashMap;
 
@@ -6,7 +8,7 @@ pub const INPUT: &str = include_str!("input");
 
 struct SystemOfEquations<'a>(HashMap<&'a str, Node<'a>>);
 
-fn parse(s: &str) -> SystemOfEquations {
+fn parse(s: &str) -> SystemOfEquations<'_> {
     SystemOfEquations(
         s.lines()
             .map(|l| {
@@ -42,6 +44,24 @@ enum Op {
 }
 
 impl Op {
+    fn symbol(self) -> char {
+        match self {
+            Op::Add => '+',
+            Op::Sub => '-',
+            Op::Mul => '*',
+            Op::Div => '/',
+        }
+    }
+
+    fn apply(self, l: i64, r: i64) -> Result<i64, Overflow> {
+        match self {
+            Op::Add => checked::add(l, r),
+            Op::Sub => checked::sub(l, r),
+            Op::Mul => checked::mul(l, r),
+            Op::Div => checked::div(l, r),
+        }
+    }
+
     fn inverse(self) -> Op {
         match self {
             Op::Add => Op::Sub,
@@ -68,25 +88,24 @@ impl Node<'_> {
 }
 
 impl<'a> SystemOfEquations<'a> {
-    fn traverse(&mut self, k: &'a str) {
-        let Some(node) = self.0.remove(k) else { return };
+    /// Evaluate everything below `k` that doesn't depend on a missing monkey
+    fn traverse(&mut self, k: &'a str) -> Result<(), Overflow> {
+        let Some(node) = self.0.remove(k) else {
+            return Ok(());
+        };
         let node = match node {
             Node::Operation(lhs, op, rhs) => {
-                self.traverse(lhs);
-                self.traverse(rhs);
+                self.traverse(lhs)?;
+                self.traverse(rhs)?;
                 match (self.get_leaf(lhs), self.get_leaf(rhs)) {
-                    (Some(l), Some(r)) => Node::Leaf(match op {
-                        Op::Add => l + r,
-                        Op::Sub => l - r,
-                        Op::Mul => l * r,
-                        Op::Div => l / r,
-                    }),
+                    (Some(l), Some(r)) => Node::Leaf(op.apply(l, r)?),
                     _ => Node::Operation(lhs, op, rhs),
                 }
             }
             leaf => leaf,
         };
         self.0.insert(k, node);
+        Ok(())
     }
 
     fn get_leaf(&self, k: &str) -> Option<i64> {
@@ -118,13 +137,121 @@ impl<'a> SystemOfEquations<'a> {
     }
 }
 
+impl<'a> SystemOfEquations<'a> {
+    /// Monkeys from `from` down to `to`
+    fn path(&self, from: &'a str, to: &str) -> Option<Vec<&'a str>> {
+        if from == to {
+            return Some(vec![from]);
+        }
+        let Some(Node::Operation(lhs, _, rhs)) = self.0.get(from) else {
+            return None;
+        };
+        let mut path = self.path(lhs, to).or_else(|| self.path(rhs, to))?;
+        path.insert(0, from);
+        Some(path)
+    }
+}
+
+impl ToDot for SystemOfEquations<'_> {
+    /// Monkey that gets highlighted along with the path from `root` to it
+    type Highlight = str;
+
+    fn to_dot(&self, target: Option<&str>) -> String {
+        let path = target
+            .and_then(|t| self.path("root", t))
+            .unwrap_or_default();
+        let on_path = |k: &str| path.contains(&k);
+        let mut dot = Dot::digraph("monkeys");
+        for (&k, node) in self.0.iter().sorted_unstable_by_key(|(k, _)| **k) {
+            let label = match node {
+                Node::Leaf(v) => format!("{k}\n{v}"),
+                Node::Operation(_, op, _) => format!("{k}\n{}", op.symbol()),
+            };
+            dot.node(k, &label, on_path(k));
+        }
+        for (&k, node) in self.0.iter().sorted_unstable_by_key(|(k, _)| **k) {
+            if let Node::Operation(lhs, _, rhs) = node {
+                for (side, operand) in [("lhs", lhs), ("rhs", rhs)] {
+                    let highlight = on_path(k) && on_path(operand);
+                    dot.edge(k, operand, Some(side), highlight);
+                }
+            }
+        }
+        dot.finish()
+    }
+}
+
+/// The monkeys' expression tree as DOT graph, optionally with the path from
+/// `root` to `humn` highlighted
+pub fn dot(s: &str, highlight: bool) -> String {
+    parse(s).to_dot(highlight.then_some("humn"))
+}
+
+#[test]
+fn sample_dot() {
+    let dot = dot(SAMPLE, true);
+    assert!(dot.starts_with("digraph \"monkeys\" {\n"));
+    assert!(dot.contains("    \"root\" [label=\"root\\n+\", color=red"));
+    assert!(dot.contains("    \"dbpl\" [label=\"dbpl\\n5\"];\n"));
+    assert!(dot.contains("    \"root\" -> \"sjmn\" [label=\"rhs\"];\n"));
+    // root -> pppw -> cczh -> lgvd -> ptdq -> humn
+    assert_eq!(dot.matches("penwidth").count(), 6 + 5);
+}
+
 pub mod part1 {
     use super::*;
 
     pub fn solution(s: &str) -> i64 {
+        try_solution(s).unwrap()
+    }
+
+    pub fn try_solution(s: &str) -> Result<i64, Overflow> {
         let mut soe = parse(s);
-        soe.traverse("root");
-        soe.get_leaf("root").unwrap()
+        soe.traverse("root")?;
+        Ok(soe.get_leaf("root").unwrap())
+    }
+
+    #[test]
+    fn limits() {
+        let max_exp = if checked::ENABLED { 63 } else { 31 };
+        for a in 0..max_exp {
+            for b in 0..max_exp {
+                let s = format!(
+                    "root: aaaa * bbbb\naaaa: {}\nbbbb: {}",
+                    1i64 << a,
+                    1i64 << b
+                );
+                match a + b {
+                    0..=62 => assert_eq!(try_solution(&s), Ok(1 << (a + b))),
+                    _ => assert_eq!(try_solution(&s), Err(Overflow(checked::Operation::Mul))),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn generated() {
+        use checked::Operation;
+        let values = checked::generate_signed(5).zip(checked::generate_signed(6));
+        for (a, b) in values.take(2000) {
+            let (x, y) = (a as i128, b as i128);
+            let ops = [
+                ("+", Some(x + y), Operation::Add),
+                ("-", Some(x - y), Operation::Sub),
+                ("*", Some(x * y), Operation::Mul),
+                ("/", x.checked_div(y), Operation::Div),
+            ];
+            for (op, exact, operation) in ops {
+                let s = format!("root: aaaa {op} bbbb\naaaa: {a}\nbbbb: {b}");
+                match exact.and_then(|v| i64::try_from(v).ok()) {
+                    Some(v) => assert_eq!(try_solution(&s), Ok(v), "{s}"),
+                    None if checked::ENABLED => {
+                        assert_eq!(try_solution(&s), Err(Overflow(operation)))
+                    }
+                    None => (),
+                }
+            }
+        }
     }
 
     #[test]
@@ -140,21 +267,27 @@ pub mod part1 {
 pub mod part2 {
     use super::*;
     pub fn solution(s: &str) -> i64 {
+        try_solution(s).unwrap()
+    }
+
+    pub fn try_solution(s: &str) -> Result<i64, Overflow> {
         let mut soe = parse(s);
         soe.0.remove("humn").unwrap();
-        soe.traverse("root");
+        soe.traverse("root")?;
 
         // In the input is written `root = lhs + rhs`
         // Either `lhs` or `rhs` has to be a leaf. To fullfill the given requrement
         // `lhs == rhs` we set the value of root to twice the leaf (rhs or lhs)
         let root_value = match soe.0.get("root").unwrap() {
-            Node::Operation(l, Op::Add, r) => 2 * soe.get_leaf(l).or(soe.get_leaf(r)).unwrap(),
+            Node::Operation(l, Op::Add, r) => {
+                checked::mul(2, soe.get_leaf(l).or(soe.get_leaf(r)).unwrap())?
+            }
             _ => panic!(),
         };
         let mut soe = soe.reversed();
         soe.0.insert("root", Node::Leaf(root_value));
-        soe.traverse("humn");
-        soe.get_leaf("humn").unwrap()
+        soe.traverse("humn")?;
+        Ok(soe.get_leaf("humn").unwrap())
     }
 
     #[test]
@@ -1,22 +1,12 @@
-//! Todo: implement proper algorithm to find adjacent edges
-//! (current implementation is buggy and only works, because the ordering was tuned manually)
+//! For part 2 the net is folded into a cube before walking: every face
+//! gets an orientation in 3D, which tells which faces share an edge.
 
 use either::Either;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 
 pub const SAMPLE: &str = include_str!("sample");
 pub const INPUT: &str = include_str!("input");
 
-const fn div_floor(lhs: i32, rhs: i32) -> i32 {
-    let d = lhs / rhs;
-    let r = lhs % rhs;
-    if (r > 0 && rhs < 0) || (r < 0 && rhs > 0) {
-        d - 1
-    } else {
-        d
-    }
-}
-
 #[derive(Hash, Eq, PartialEq, Clone, Debug)]
 struct P {
     x: i32,
@@ -112,17 +102,141 @@ impl P {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 struct State {
     p: P,
     dir: Dir,
 }
 
+/// Face and direction one ends up with when leaving a face in each direction
+type Edges = [(P, Dir); 4];
+
 #[derive(Debug)]
-struct Map<const TILE_SIZE: usize> {
+struct Map {
     ps: HashMap<P, Field>,
     x_max: i32,
     y_max: i32,
+    /// Only set once the board is folded, see [`Map::with_cube`]
+    cube: Option<Cube>,
+}
+
+#[derive(Debug)]
+struct Cube {
+    face_size: i32,
+    /// Faces by their position in the net, in units of `face_size`
+    faces: HashMap<P, Edges>,
+}
+
+type V3 = [i32; 3];
+
+fn neg([x, y, z]: V3) -> V3 {
+    [-x, -y, -z]
+}
+
+/// Orientation of a face on the cube: outward normal and the directions
+/// of the face's x and y axis
+#[derive(Clone, Copy)]
+struct Frame {
+    normal: V3,
+    right: V3,
+    down: V3,
+}
+
+impl Frame {
+    /// Vector pointing out of the face's edge in `dir`
+    fn outward(&self, dir: &Dir) -> V3 {
+        match dir {
+            Dir::Right => self.right,
+            Dir::Down => self.down,
+            Dir::Left => neg(self.right),
+            Dir::Up => neg(self.down),
+        }
+    }
+
+    /// Orientation of the face behind the edge in `dir` once folded
+    fn fold(self, dir: &Dir) -> Frame {
+        let Frame {
+            normal,
+            right,
+            down,
+        } = self;
+        match dir {
+            Dir::Right => Frame {
+                normal: right,
+                right: neg(normal),
+                down,
+            },
+            Dir::Down => Frame {
+                normal: down,
+                right,
+                down: neg(normal),
+            },
+            Dir::Left => Frame {
+                normal: neg(right),
+                right: normal,
+                down,
+            },
+            Dir::Up => Frame {
+                normal: neg(down),
+                right,
+                down: normal,
+            },
+        }
+    }
+}
+
+const DIRS: [Dir; 4] = [Dir::Right, Dir::Down, Dir::Left, Dir::Up];
+
+/// Fold the net by walking from face to face, then connect every edge to the
+/// face that points the same way
+fn fold(ps: &HashMap<P, Field>, face_size: i32) -> HashMap<P, Edges> {
+    let first = ps
+        .keys()
+        .map(|p| P {
+            x: p.x / face_size,
+            y: p.y / face_size,
+        })
+        .min_by_key(|p| (p.y, p.x))
+        .unwrap();
+    let mut frames = HashMap::from([(
+        first.clone(),
+        Frame {
+            normal: [0, 0, -1],
+            right: [1, 0, 0],
+            down: [0, 1, 0],
+        },
+    )]);
+    let mut queue = VecDeque::from([first]);
+    while let Some(face) = queue.pop_front() {
+        let frame = frames[&face];
+        for dir in DIRS {
+            let next = face.clone().step(&dir);
+            let corner = next.clone() * face_size;
+            if ps.contains_key(&corner) && !frames.contains_key(&next) {
+                frames.insert(next.clone(), frame.fold(&dir));
+                queue.push_back(next);
+            }
+        }
+    }
+    assert_eq!(frames.len(), 6, "The net needs six faces");
+    frames
+        .iter()
+        .map(|(face, frame)| {
+            let edges = DIRS.map(|dir| {
+                let (next, next_frame) = frames
+                    .iter()
+                    .find(|(_, f)| f.normal == frame.outward(&dir))
+                    .unwrap();
+                // Enter through the edge that points back to where we came from
+                let entry = DIRS
+                    .into_iter()
+                    .find(|d| next_frame.outward(d) == frame.normal)
+                    .unwrap();
+                (next.clone(), entry.shift(Dir::Left))
+            });
+            (face.clone(), edges)
+        })
+        .collect()
 }
 
 #[derive(Debug)]
@@ -138,13 +252,11 @@ enum Instruction {
 }
 use Instruction::*;
 
-fn parse<const TILE_SIZE: usize>(
-    s: &str,
-) -> (Map<TILE_SIZE>, impl Iterator<Item = Instruction> + '_) {
+fn parse(s: &str) -> (Map, impl Iterator<Item = Instruction> + '_) {
     let (s1, s2) = s.split_once("\n\n").unwrap();
     let mut x_max = 0;
     let mut y_max = 0;
-    let ps = s1
+    let ps: HashMap<_, _> = s1
         .lines()
         .enumerate()
         .flat_map(|(y, l)| {
@@ -179,14 +291,39 @@ fn parse<const TILE_SIZE: usize>(
         };
         once(Go(go.parse().unwrap())).chain(lr.map(Turn))
     });
-    (Map { ps, x_max, y_max }, instr)
+    (
+        Map {
+            ps,
+            x_max,
+            y_max,
+            cube: None,
+        },
+        instr,
+    )
 }
 
 fn calc_password(state: State) -> i32 {
     1000 * (state.p.y + 1) + 4 * (state.p.x + 1) + state.dir as i32
 }
 
-impl<const TILE_SIZE: usize> Map<TILE_SIZE> {
+impl Map {
+    /// Fold the board into a cube, which is needed to walk on it in part 2
+    fn with_cube(mut self) -> Self {
+        let face_size = ((self.ps.len() / 6) as f64).sqrt() as i32;
+        assert_eq!(
+            6 * face_size * face_size,
+            self.ps.len() as i32,
+            "The map can't be folded into a cube"
+        );
+        let faces = fold(&self.ps, face_size);
+        self.cube = Some(Cube { face_size, faces });
+        self
+    }
+
+    fn cube(&self) -> &Cube {
+        self.cube.as_ref().expect("The map isn't folded")
+    }
+
     fn first_field(&self, state: State) -> (P, &Field) {
         self.wrapped_points(state)
             .find_map(|p| self.ps.get(&p).map(|f| (p, f)))
@@ -217,15 +354,27 @@ impl<const TILE_SIZE: usize> Map<TILE_SIZE> {
             .unwrap_or_else(|| self.first_field(State { p, dir }))
     }
 
-    fn perform_part1(&self, State { mut p, dir }: State, instr: Instruction) -> State {
+    /// Pushes every state on the way to `history`, if given
+    fn perform_part1(
+        &self,
+        State { mut p, dir }: State,
+        instr: Instruction,
+        mut history: Option<&mut Vec<State>>,
+    ) -> State {
         match instr {
-            Turn(lr) => State {
-                p,
-                dir: match lr {
-                    LR::L => dir.shift(Dir::Up),
-                    LR::R => dir.shift(Dir::Down),
-                },
-            },
+            Turn(lr) => {
+                let state = State {
+                    p,
+                    dir: match lr {
+                        LR::L => dir.shift(Dir::Up),
+                        LR::R => dir.shift(Dir::Down),
+                    },
+                };
+                if let Some(history) = history {
+                    history.push(state.clone());
+                }
+                state
+            }
             Go(n) => {
                 for _ in 0..n {
                     match self.wrap_step(State {
@@ -235,94 +384,64 @@ impl<const TILE_SIZE: usize> Map<TILE_SIZE> {
                         (_, Field::Wall) => break,
                         (new_p, Field::Free) => p = new_p,
                     }
+                    if let Some(history) = history.as_deref_mut() {
+                        history.push(State {
+                            p: p.clone(),
+                            dir: dir.clone(),
+                        });
+                    }
                 }
                 State { p, dir }
             }
         }
     }
 
-    fn wrap_cube(&self, State { mut p, dir }: State) -> (State, &Field) {
-        p = p.step(&dir);
-        self.ps
-            .get(&p)
-            .map(|f| {
-                (
-                    State {
-                        p: p.clone(),
-                        dir: dir.clone(),
-                    },
-                    f,
-                )
-            })
-            .unwrap_or_else(|| {
-                let tile_size = TILE_SIZE as i32;
-                let next_p_tile = P {
-                    x: (p.x + tile_size) % tile_size,
-                    y: (p.y + tile_size) % tile_size,
-                };
-                let tile_offset = P {
-                    x: div_floor(p.x, tile_size) * tile_size,
-                    y: div_floor(p.y, tile_size) * tile_size,
-                };
-                [
-                    // const FACES: [(P, Dir); 23] = [
-                    // len == 3
-                    (P { x: 0, y: -1 }, Dir::Up),
-                    (P { x: 0, y: 1 }, Dir::Down),
-                    // len == 6
-                    // (P { x: -2, y: -4 }, Dir::Right),
-                    // (P { x: -2, y: 4 }, Dir::Right),
-                    // (P { x: -3, y: -3 }, Dir::Right),
-                    // (P { x: -3, y: 3 }, Dir::Right),
-                    (P { x: -4, y: -2 }, Dir::Right),
-                    // (P { x: -4, y: 2 }, Dir::Right),
-                    // len == 5
-                    // (P { x: 0, y: -3 }, Dir::Down),
-                    // (P { x: 0, y: 3 }, Dir::Up),
-                    // (P { x: -3, y: -2 }, Dir::Down),
-                    // (P { x: -3, y: 2 }, Dir::Up),
-                    (P { x: -4, y: -1 }, Dir::Down),
-                    // (P { x: -4, y: 1 }, Dir::Up),
-                    // (P { x: -2, y: -3 }, Dir::Down),
-                    (P { x: -2, y: 3 }, Dir::Up),
-                    // (P { x: -4, y: -1 }, Dir::Up), // Ambiguous
-                    // (P { x: -4, y: 1 }, Dir::Down), // Ambiguous
-                    // len == 4
-                    // (P { x: -4, y: 0 }, Dir::Right), // Symmetric
-                    // (P { x: -2, y: -2 }, Dir::Left),
-                    (P { x: -2, y: 2 }, Dir::Left),
-                    (P { x: 0, y: -2 }, Dir::Left),
-                    // (P { x: 0, y: 2 }, Dir::Left),
-                ]
-                .into_iter()
-                .find_map(|(f, next_dir)| {
-                    let next_p = tile_offset.clone()
-                        + next_p_tile.clone().tfm_rotate(&next_dir, tile_size - 1)
-                        + f.tfm_rotate(&dir, 0) * tile_size;
-                    self.ps.get(&next_p).map(|field| {
-                        let next_dir = next_dir.shift(dir.clone());
-                        (
-                            State {
-                                p: next_p,
-                                dir: next_dir,
-                            },
-                            field,
-                        )
-                    })
-                })
-                .unwrap()
-            })
+    fn wrap_cube(&self, State { p, dir }: State) -> (State, &Field) {
+        let Cube {
+            face_size: n,
+            faces,
+        } = self.cube();
+        let n = *n;
+        let face = P {
+            x: p.x / n,
+            y: p.y / n,
+        };
+        let p = p.step(&dir);
+        if let Some(f) = self.ps.get(&p) {
+            return (State { p, dir }, f);
+        }
+        let (next_face, next_dir) = faces[&face][dir.clone() as usize].clone();
+        let local = P {
+            x: p.x.rem_euclid(n),
+            y: p.y.rem_euclid(n),
+        };
+        let rotation = Dir::from((next_dir.clone() as i8 - dir as i8).rem_euclid(4));
+        let p = local.tfm_rotate(&rotation, n - 1) + next_face * n;
+        let field = &self.ps[&p];
+        (State { p, dir: next_dir }, field)
     }
 
-    fn perform_part2(&self, State { mut p, mut dir }: State, instr: Instruction) -> State {
+    /// Pushes every state on the way to `history`, if given
+    fn perform_part2(
+        &self,
+        State { mut p, mut dir }: State,
+        instr: Instruction,
+        mut history: Option<&mut Vec<State>>,
+    ) -> State {
         match instr {
-            Turn(lr) => State {
-                p,
-                dir: match lr {
-                    LR::L => dir.shift(Dir::Up),
-                    LR::R => dir.shift(Dir::Down),
-                },
-            },
+            Turn(lr) => {
+                let state = State {
+                    p,
+                    dir: match lr {
+                        LR::L => dir.shift(Dir::Up),
+                        LR::R => dir.shift(Dir::Down),
+                    },
+                };
+                if let Some(history) = history {
+                    history.push(state.clone());
+                }
+                state
+            }
             Go(n) => {
                 for _ in 0..n {
                     match self.wrap_cube(State {
@@ -341,6 +460,12 @@ impl<const TILE_SIZE: usize> Map<TILE_SIZE> {
                             dir = new_dir
                         }
                     }
+                    if let Some(history) = history.as_deref_mut() {
+                        history.push(State {
+                            p: p.clone(),
+                            dir: dir.clone(),
+                        });
+                    }
                 }
                 State { p, dir }
             }
@@ -348,26 +473,186 @@ impl<const TILE_SIZE: usize> Map<TILE_SIZE> {
     }
 }
 
+impl Dir {
+    fn arrow(&self) -> char {
+        match self {
+            Dir::Right => '>',
+            Dir::Down => 'v',
+            Dir::Left => '<',
+            Dir::Up => '^',
+        }
+    }
+
+    fn from_arrow(c: char) -> Option<Dir> {
+        DIRS.into_iter().find(|d| d.arrow() == c)
+    }
+}
+
+/// Follow the instructions, on the flat board or on the `cube`, and return
+/// the map with the final state
+///
+/// Every visited state is pushed to `history`, if given.
+fn walk(s: &str, cube: bool, mut history: Option<&mut Vec<State>>) -> (Map, State) {
+    let (map, instructions) = parse(s);
+    let map = if cube { map.with_cube() } else { map };
+    let dir = Dir::Right;
+    let start = State {
+        p: map.first_p(State {
+            p: P { x: 0, y: 0 },
+            dir: dir.clone(),
+        }),
+        dir,
+    };
+    if let Some(history) = history.as_deref_mut() {
+        history.push(start.clone());
+    }
+    let end = instructions.fold(start, |s, instr| match cube {
+        false => map.perform_part1(s, instr, history.as_deref_mut()),
+        true => map.perform_part2(s, instr, history.as_deref_mut()),
+    });
+    (map, end)
+}
+
+impl Map {
+    /// Board with the last facing on every visited tile
+    fn trace(&self, history: &[State]) -> Vec<Vec<char>> {
+        let mut rows = vec![vec![' '; self.x_max as usize]; self.y_max as usize + 1];
+        for (p, field) in &self.ps {
+            rows[p.y as usize][p.x as usize] = match field {
+                Field::Wall => '#',
+                Field::Free => '.',
+            };
+        }
+        for State { p, dir } in history {
+            rows[p.y as usize][p.x as usize] = dir.arrow();
+        }
+        rows
+    }
+
+    /// Every face of the traced board on its own, surrounded by the border
+    /// tiles of its neighbours as seen when stepping over the edge
+    fn unfold(&self, rows: &[Vec<char>]) -> String {
+        let Cube {
+            face_size: n,
+            faces,
+        } = self.cube();
+        let n = *n;
+        let mut faces: Vec<_> = faces.keys().cloned().collect();
+        faces.sort_by_key(|f| (f.y, f.x));
+        let mut out = String::new();
+        for (i, face) in faces.into_iter().enumerate() {
+            out += &format!("Face {} at ({}, {})\n", i + 1, face.x * n, face.y * n);
+            for y in -1..=n {
+                for x in -1..=n {
+                    let inside = |v| (0..n).contains(&v);
+                    let c = match (inside(x), inside(y)) {
+                        (true, true) => {
+                            let p = face.clone() * n + P { x, y };
+                            rows[p.y as usize][p.x as usize]
+                        }
+                        (false, false) => ' ',
+                        _ => {
+                            let dir = match (x, y) {
+                                (x, _) if x == n => Dir::Right,
+                                (_, y) if y == n => Dir::Down,
+                                (-1, _) => Dir::Left,
+                                _ => Dir::Up,
+                            };
+                            let edge = P {
+                                x: x.clamp(0, n - 1),
+                                y: y.clamp(0, n - 1),
+                            };
+                            let from = State {
+                                p: face.clone() * n + edge,
+                                dir: dir.clone(),
+                            };
+                            let (State { p, dir: next_dir }, _) = self.wrap_cube(from);
+                            let c = rows[p.y as usize][p.x as usize];
+                            // Turn arrows back into the orientation of this face
+                            let rotation = (dir as i8 - next_dir as i8).rem_euclid(4);
+                            Dir::from_arrow(c).map_or(c, |d| d.shift(Dir::from(rotation)).arrow())
+                        }
+                    };
+                    out.push(c);
+                }
+                out.push('\n');
+            }
+        }
+        out
+    }
+}
+
+fn render(rows: &[Vec<char>]) -> String {
+    rows.iter()
+        .map(|row| row.iter().collect::<String>().trim_end().to_owned() + "\n")
+        .collect()
+}
+
+#[test]
+fn folding() {
+    for (s, face_size) in [(SAMPLE, 4), (INPUT, 50)] {
+        let map = parse(s).0.with_cube();
+        let cube = map.cube();
+        assert_eq!(cube.face_size, face_size);
+        for (face, edges) in &cube.faces {
+            for (dir, (next, entry)) in DIRS.iter().zip(edges) {
+                // Turning around on the other side leads back over the same edge
+                let back = entry.clone().shift(Dir::Left) as usize;
+                let opposite = dir.clone().shift(Dir::Left);
+                assert_eq!(cube.faces[next][back], (face.clone(), opposite));
+            }
+        }
+    }
+    // The examples of the puzzle text: going right from A on the face at
+    // (2, 1) continues downwards on the face at (3, 2), and going down from C
+    // on the face at (2, 2) continues upwards on the face at (0, 1)
+    let map = parse(SAMPLE).0.with_cube();
+    let faces = &map.cube().faces;
+    assert_eq!(faces[&P { x: 2, y: 1 }][0], (P { x: 3, y: 2 }, Dir::Down));
+    assert_eq!(faces[&P { x: 2, y: 2 }][1], (P { x: 0, y: 1 }, Dir::Up));
+}
+
 pub mod part1 {
     use super::*;
 
     pub fn solution(s: &str) -> i32 {
-        let (map, instructions) = parse::<0>(s);
-        let dir = Dir::Right;
-        let mut state = State {
-            p: map.first_p(State {
-                p: P { x: 0, y: 0 },
-                dir: dir.clone(),
-            }),
-            dir,
-        };
-        state = instructions.fold(state, |s, instr| map.perform_part1(s, instr));
-        calc_password(state)
+        let (_, end) = walk(s, false, None);
+        calc_password(end)
+    }
+
+    /// Board with the path drawn like in the puzzle text
+    pub fn render(s: &str) -> String {
+        let mut history = Vec::new();
+        let (map, _) = walk(s, false, Some(&mut history));
+        super::render(&map.trace(&history))
+    }
+
+    #[test]
+    fn rendered() {
+        assert_eq!(
+            render(SAMPLE),
+            concat!(
+                "        >>v#\n",
+                "        .#v.\n",
+                "        #.v.\n",
+                "        ..v.\n",
+                "...#...v..v#\n",
+                ">>>v...>#.>>\n",
+                "..#v...#....\n",
+                "...>>>>v..#.\n",
+                "        ...#....\n",
+                "        .....#..\n",
+                "        .#......\n",
+                "        ......#.\n",
+            )
+        );
     }
 
     #[test]
     fn sample() {
         assert_eq!(solution(SAMPLE), 6032);
+        // Any board shape works without folding
+        assert_eq!(solution("...\n.#\n\n4R1"), 1000 + 4 * 2 + 1);
     }
     #[test]
     fn actual() {
@@ -378,26 +663,61 @@ pub mod part1 {
 pub mod part2 {
     use super::*;
 
-    pub fn solution<const TILE_SIZE: usize>(s: &str) -> i32 {
-        let (map, instructions) = parse::<TILE_SIZE>(s);
-        let dir = Dir::Right;
-        let mut state = State {
-            p: map.first_p(State {
-                p: P { x: 0, y: 0 },
-                dir: dir.clone(),
-            }),
-            dir,
-        };
-        state = instructions.fold(state, |s, instr| map.perform_part2(s, instr));
-        calc_password(state)
+    pub fn solution(s: &str) -> i32 {
+        let (_, end) = walk(s, true, None);
+        calc_password(end)
+    }
+
+    /// Board with the path drawn like in the puzzle text
+    pub fn render(s: &str) -> String {
+        let mut history = Vec::new();
+        let (map, _) = walk(s, true, Some(&mut history));
+        super::render(&map.trace(&history))
+    }
+
+    /// Every face separately, with the tiles behind its edges around it
+    pub fn render_faces(s: &str) -> String {
+        let mut history = Vec::new();
+        let (map, _) = walk(s, true, Some(&mut history));
+        map.unfold(&map.trace(&history))
+    }
+
+    #[test]
+    fn rendered() {
+        assert_eq!(
+            render(SAMPLE),
+            concat!(
+                "        >>v#\n",
+                "        .#v.\n",
+                "        #.v.\n",
+                "        ..v.\n",
+                "...#..^...v#\n",
+                ".>>>>>^.#.>>\n",
+                ".^#....#....\n",
+                ".^........#.\n",
+                "        ...#..v.\n",
+                "        .....#v.\n",
+                "        .#v<<<<.\n",
+                "        ..v...#.\n",
+            )
+        );
+        assert!(render_faces(SAMPLE).starts_with(concat!(
+            "Face 1 at (8, 0)\n",
+            " #... \n",
+            ".>>v#.\n",
+            "..#v..\n",
+            ">#.v..\n",
+            "...v..\n",
+            " ..v# \n",
+        )));
     }
 
     #[test]
     fn sample() {
-        assert_eq!(solution::<4>(SAMPLE), 5031);
+        assert_eq!(solution(SAMPLE), 5031);
     }
     #[test]
     fn actual() {
-        assert_eq!(solution::<50>(INPUT), 110400);
+        assert_eq!(solution(INPUT), 110400);
     }
 }
@@ -1,14 +1,13 @@
 use itertools::Itertools;
-use std::collections::{HashMap, HashSet};
 use std::ops;
 
 pub const SAMPLE: &str = include_str!("sample");
 pub const INPUT: &str = include_str!("input");
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
-struct P {
-    x: i32,
-    y: i32,
+pub struct P {
+    pub x: i32,
+    pub y: i32,
 }
 
 const fn add(lhs: &P, rhs: &P) -> P {
@@ -19,44 +18,7 @@ const fn add(lhs: &P, rhs: &P) -> P {
 }
 impl_op_ex!(+ |a: &P, b: &P| -> P { add(a, b) });
 
-fn move_elves(elves: &mut HashSet<P>, orientations: &mut [[P; 3]; 4]) -> Result<(), ()> {
-    let (candidates, mut frozen): (Vec<P>, Vec<P>) = elves
-        .iter()
-        .cloned()
-        .partition(|p| SURROUND.iter().any(|rel_p| elves.contains(&(p + rel_p))));
-    let mut moved = HashMap::<P, P>::new();
-    candidates.into_iter().for_each(|candidate| {
-        if let Some(next_p) = orientations.iter().find_map(|ps| {
-            if ps
-                .iter()
-                .all(|rel_p| !elves.contains(&(&candidate + rel_p)))
-            {
-                Some(&candidate + &ps[0])
-            } else {
-                None
-            }
-        }) {
-            if let Some(conflicting) = moved.remove(&next_p) {
-                frozen.push(conflicting);
-                frozen.push(candidate);
-            } else {
-                moved.insert(next_p, candidate);
-            }
-        } else {
-            frozen.push(candidate);
-        }
-    });
-    let nothing_moved = moved.is_empty();
-    *elves = HashSet::from_iter(moved.into_keys().chain(frozen));
-    orientations.rotate_left(1);
-    if nothing_moved {
-        Err(())
-    } else {
-        Ok(())
-    }
-}
-
-fn parse(s: &str) -> HashSet<P> {
+fn parse(s: &str) -> Vec<P> {
     s.lines()
         .enumerate()
         .flat_map(|(y, l)| {
@@ -72,56 +34,283 @@ fn parse(s: &str) -> HashSet<P> {
         .collect()
 }
 
-const N: P = P { x: 0, y: -1 };
-const S: P = P { x: 0, y: 1 };
-const W: P = P { x: -1, y: 0 };
-const E: P = P { x: 1, y: 0 };
-
-const SURROUND: [P; 8] = [
-    N,
-    add(&N, &E),
-    E,
-    add(&S, &E),
-    S,
-    add(&S, &W),
-    W,
-    add(&W, &N),
-];
-
-type Orientation = [P; 3];
-const INIT_ORIENTATIONS: [Orientation; 4] = [
-    [N, add(&N, &E), add(&N, &W)],
-    [S, add(&S, &E), add(&S, &W)],
-    [W, add(&N, &W), add(&S, &W)],
-    [E, add(&N, &E), add(&S, &E)],
-];
-
-fn _print_map(ps: &HashSet<P>) -> impl Iterator<Item = u8> + '_ {
-    let (min_x, max_x) = ps.iter().map(|p| p.x).minmax().into_option().unwrap();
-    let (min_y, max_y) = ps.iter().map(|p| p.y).minmax().into_option().unwrap();
-    (min_y..=max_y).flat_map(move |y| {
-        (min_x..=max_x)
-            .map(move |x| if ps.contains(&P { x, y }) { b'#' } else { b'.' })
-            .chain(std::iter::once(b'\n'))
-    })
+/// Direction an elf considers moving to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Dir {
+    N = 0,
+    S = 1,
+    W = 2,
+    E = 3,
 }
 
-pub mod part1 {
-    use super::*;
+pub const INIT_ORIENTATIONS: [Dir; 4] = [Dir::N, Dir::S, Dir::W, Dir::E];
 
-    pub fn solution(s: &str) -> i32 {
-        let mut elves = parse(s);
+type Row = Vec<u64>;
 
-        let mut orientations = INIT_ORIENTATIONS;
+/// Row `r` moved one column east, evaluated at word `i`
+fn shift_e(r: &[u64], i: usize) -> u64 {
+    (r[i] << 1) | if i > 0 { r[i - 1] >> 63 } else { 0 }
+}
 
-        for _ in 0..10 {
-            move_elves(&mut elves, &mut orientations).ok();
+/// Row `r` moved one column west, evaluated at word `i`
+fn shift_w(r: &[u64], i: usize) -> u64 {
+    (r[i] >> 1) | r.get(i + 1).map_or(0, |w| w << 63)
+}
+
+/// Elves stored as one bit per cell, with every row split into 64 bit words
+///
+/// A round is computed for 64 elves at once. The grid grows, so that the
+/// outermost rows and columns are always empty.
+pub struct Grove {
+    rows: Vec<Row>,
+    /// Position of bit 0 in row 0
+    origin: P,
+    orientations: [Dir; 4],
+}
+
+impl Grove {
+    pub fn new(s: &str, orientations: [Dir; 4]) -> Grove {
+        assert!(
+            orientations.iter().all_unique(),
+            "Every direction has to be considered exactly once"
+        );
+        let elves = parse(s);
+        let height = elves.iter().map(|p| p.y + 1).max().unwrap_or(0) as usize;
+        let width = elves.iter().map(|p| p.x + 1).max().unwrap_or(0) as usize;
+        let mut rows = vec![vec![0; width.div_ceil(64).max(1)]; height];
+        elves
+            .iter()
+            .for_each(|p| rows[p.y as usize][p.x as usize / 64] |= 1 << (p.x % 64));
+        let mut grove = Grove {
+            rows,
+            origin: P { x: 0, y: 0 },
+            orientations,
+        };
+        grove.ensure_margin();
+        grove
+    }
+
+    fn ensure_margin(&mut self) {
+        if self.rows.first().is_none_or(|r| r.iter().any(|w| *w != 0)) {
+            self.rows
+                .insert(0, vec![0; self.rows.first().map_or(1, Vec::len)]);
+            self.origin.y -= 1;
+        }
+        if self.rows.last().unwrap().iter().any(|w| *w != 0) {
+            self.rows.push(vec![0; self.rows[0].len()]);
+        }
+        if self.rows.iter().any(|r| r[0] & 1 != 0) {
+            self.rows.iter_mut().for_each(|r| r.insert(0, 0));
+            self.origin.x -= 64;
+        }
+        if self.rows.iter().any(|r| r.last().unwrap() >> 63 != 0) {
+            self.rows.iter_mut().for_each(|r| r.push(0));
+        }
+    }
+
+    /// Perform one round, returns whether any elf moved
+    pub fn round(&mut self) -> bool {
+        self.ensure_margin();
+        let (height, n_words) = (self.rows.len(), self.rows[0].len());
+        let rows = &self.rows;
+
+        // Proposed moves per direction, at the position of the proposing elf
+        let mut proposals: [Vec<Row>; 4] = std::array::from_fn(|_| vec![vec![0; n_words]; height]);
+        for y in 1..height - 1 {
+            let (n, c, s) = (&rows[y - 1], &rows[y], &rows[y + 1]);
+            for i in 0..n_words {
+                let free = |r: &[u64]| !(r[i] | shift_e(r, i) | shift_w(r, i));
+                let free = [
+                    free(n),
+                    free(s),
+                    !(shift_e(n, i) | shift_e(c, i) | shift_e(s, i)),
+                    !(shift_w(n, i) | shift_w(c, i) | shift_w(s, i)),
+                ];
+                let mut undecided = c[i] & !free.iter().fold(!0, |acc, f| acc & f);
+                for d in self.orientations {
+                    let p = undecided & free[d as usize];
+                    proposals[d as usize][y][i] = p;
+                    undecided &= !p;
+                }
+            }
         }
+
+        // Only elves moving in opposite directions can propose the same cell
+        let [north, south, west, east] = &proposals;
+        let zero = vec![0; n_words];
+        let (collide_v, collide_h): (Vec<Row>, Vec<Row>) = (0..height)
+            .map(|y| {
+                let from_s = north.get(y + 1).unwrap_or(&zero);
+                let from_n = y.checked_sub(1).map_or(&zero, |y| &south[y]);
+                (0..n_words)
+                    .map(|i| {
+                        (
+                            from_s[i] & from_n[i],
+                            shift_w(&west[y], i) & shift_e(&east[y], i),
+                        )
+                    })
+                    .unzip()
+            })
+            .unzip();
+
+        let mut moved = false;
+        let mut next = rows.clone();
+        for (y, row) in next.iter_mut().enumerate() {
+            for (i, word) in row.iter_mut().enumerate() {
+                let from_s = north.get(y + 1).map_or(0, |r| r[i]);
+                let from_n = y.checked_sub(1).map_or(0, |y| south[y][i]);
+                let arriving = (from_s | from_n | shift_w(&west[y], i) | shift_e(&east[y], i))
+                    & !collide_v[y][i]
+                    & !collide_h[y][i];
+                let leaving = (north[y][i] & !y.checked_sub(1).map_or(0, |y| collide_v[y][i]))
+                    | (south[y][i] & !collide_v.get(y + 1).map_or(0, |r| r[i]))
+                    | (west[y][i] & !shift_e(&collide_h[y], i))
+                    | (east[y][i] & !shift_w(&collide_h[y], i));
+                moved |= leaving != 0;
+                *word = (*word & !leaving) | arriving;
+            }
+        }
+        self.rows = next;
+        self.orientations.rotate_left(1);
+        moved
+    }
+
+    pub fn elves(&self) -> impl Iterator<Item = P> + '_ {
+        self.rows.iter().enumerate().flat_map(move |(y, row)| {
+            row.iter().enumerate().flat_map(move |(i, w)| {
+                (0..64).filter(move |b| w & (1 << b) != 0).map(move |b| P {
+                    x: self.origin.x + (i * 64 + b) as i32,
+                    y: self.origin.y + y as i32,
+                })
+            })
+        })
+    }
+
+    /// Empty cells within the bounding box of all elves
+    pub fn empty_ground(&self) -> i32 {
+        let elves = self.elves().collect_vec();
         let (min_x, max_x) = elves.iter().map(|p| p.x).minmax().into_option().unwrap();
         let (min_y, max_y) = elves.iter().map(|p| p.y).minmax().into_option().unwrap();
         (max_y - min_y + 1) * (max_x - min_x + 1) - elves.len() as i32
     }
 
+    fn _print_map(&self) -> String {
+        let ps: std::collections::HashSet<P> = self.elves().collect();
+        let (min_x, max_x) = ps.iter().map(|p| p.x).minmax().into_option().unwrap();
+        let (min_y, max_y) = ps.iter().map(|p| p.y).minmax().into_option().unwrap();
+        (min_y..=max_y)
+            .flat_map(|y| {
+                (min_x..=max_x)
+                    .map(|x| if ps.contains(&P { x, y }) { '#' } else { '.' })
+                    .chain(std::iter::once('\n'))
+                    .collect_vec()
+            })
+            .collect()
+    }
+}
+
+/// The previous implementation based on a `HashSet` of elves
+#[cfg(test)]
+mod reference {
+    use super::*;
+    use std::collections::{HashMap, HashSet};
+
+    const N: P = P { x: 0, y: -1 };
+    const S: P = P { x: 0, y: 1 };
+    const W: P = P { x: -1, y: 0 };
+    const E: P = P { x: 1, y: 0 };
+
+    const SURROUND: [P; 8] = [
+        N,
+        add(&N, &E),
+        E,
+        add(&S, &E),
+        S,
+        add(&S, &W),
+        W,
+        add(&W, &N),
+    ];
+
+    fn orientation(d: Dir) -> [P; 3] {
+        match d {
+            Dir::N => [N, add(&N, &E), add(&N, &W)],
+            Dir::S => [S, add(&S, &E), add(&S, &W)],
+            Dir::W => [W, add(&N, &W), add(&S, &W)],
+            Dir::E => [E, add(&N, &E), add(&S, &E)],
+        }
+    }
+
+    fn move_elves(elves: &mut HashSet<P>, orientations: &mut [[P; 3]; 4]) -> Result<(), ()> {
+        let (candidates, mut frozen): (Vec<P>, Vec<P>) = elves
+            .iter()
+            .cloned()
+            .partition(|p| SURROUND.iter().any(|rel_p| elves.contains(&(p + rel_p))));
+        let mut moved = HashMap::<P, P>::new();
+        candidates.into_iter().for_each(|candidate| {
+            if let Some(next_p) = orientations.iter().find_map(|ps| {
+                if ps
+                    .iter()
+                    .all(|rel_p| !elves.contains(&(&candidate + rel_p)))
+                {
+                    Some(&candidate + &ps[0])
+                } else {
+                    None
+                }
+            }) {
+                if let Some(conflicting) = moved.remove(&next_p) {
+                    frozen.push(conflicting);
+                    frozen.push(candidate);
+                } else {
+                    moved.insert(next_p, candidate);
+                }
+            } else {
+                frozen.push(candidate);
+            }
+        });
+        let nothing_moved = moved.is_empty();
+        *elves = HashSet::from_iter(moved.into_keys().chain(frozen));
+        orientations.rotate_left(1);
+        if nothing_moved {
+            Err(())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn assert_same(s: &str, order: [Dir; 4], rounds: usize) {
+        let mut grove = Grove::new(s, order);
+        let mut elves: HashSet<P> = parse(s).into_iter().collect();
+        let mut orientations = order.map(orientation);
+        for round in 1..=rounds {
+            let moved = grove.round();
+            assert_eq!(moved, move_elves(&mut elves, &mut orientations).is_ok());
+            assert_eq!(
+                grove.elves().collect::<HashSet<_>>(),
+                elves,
+                "round {round}"
+            );
+        }
+    }
+
+    #[test]
+    fn identical() {
+        assert_same(SAMPLE, INIT_ORIENTATIONS, 25);
+        assert_same(SAMPLE, [Dir::E, Dir::W, Dir::S, Dir::N], 25);
+        assert_same(INPUT, INIT_ORIENTATIONS, 100);
+    }
+}
+
+pub mod part1 {
+    use super::*;
+
+    pub fn solution(s: &str) -> i32 {
+        let mut grove = Grove::new(s, INIT_ORIENTATIONS);
+        for _ in 0..10 {
+            grove.round();
+        }
+        grove.empty_ground()
+    }
+
     #[test]
     fn sample() {
         assert_eq!(solution(SAMPLE), 110);
@@ -135,12 +324,8 @@ pub mod part2 {
     use super::*;
 
     pub fn solution(s: &str) -> i32 {
-        let mut elves = parse(s);
-        let mut orientations = INIT_ORIENTATIONS;
-
-        (1..)
-            .find(|_| move_elves(&mut elves, &mut orientations).is_err())
-            .unwrap()
+        let mut grove = Grove::new(s, INIT_ORIENTATIONS);
+        (1..).find(|_| !grove.round()).unwrap()
     }
 
     #[test]
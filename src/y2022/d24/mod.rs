@@ -1,14 +1,13 @@
-use std::collections::HashSet;
-use std::iter::once;
+use crate::math;
 use std::ops;
 
 pub const SAMPLE: &str = include_str!("sample");
 pub const INPUT: &str = include_str!("input");
 
 #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
-struct P {
-    x: i32,
-    y: i32,
+pub struct P {
+    pub x: i32,
+    pub y: i32,
 }
 
 impl_op_ex!(+ |l: &P, r: &P| -> P {P {x: l.x + r.x, y: l.y + r.y}});
@@ -17,110 +16,122 @@ impl P {
     fn within_bbox(&self, other: &P) -> bool {
         other.x >= 0 && other.y >= 0 && other.x < self.x && other.y < self.y
     }
+}
 
-    fn tick(&mut self, t_max: &P) {
-        self.x = (self.x + 1) % t_max.x;
-        self.y = (self.y + 1) % t_max.y;
+const ZERO: P = P { x: 0, y: 0 };
+const N: P = P { x: 0, y: -1 };
+const E: P = P { x: 1, y: 0 };
+const S: P = P { x: 0, y: 1 };
+const W: P = P { x: -1, y: 0 };
+
+/// Fixed size set of cell indices
+#[derive(Clone)]
+struct BitSet(Vec<u64>);
+
+impl BitSet {
+    fn new(len: usize) -> BitSet {
+        BitSet(vec![0; len.div_ceil(64)])
     }
-}
 
-#[derive(PartialEq, Eq, Hash, Clone, Debug)]
-struct State {
-    /// current position
-    p: P,
-    /// time wrapped separately for x and y
-    t: P,
-}
+    fn contains(&self, i: usize) -> bool {
+        self.0[i / 64] & (1 << (i % 64)) != 0
+    }
 
-fn wrap(v: i32, wrap: i32) -> i32 {
-    if v < 0 {
-        v + wrap
-    } else {
-        v
+    /// Returns whether `i` was newly inserted
+    fn insert(&mut self, i: usize) -> bool {
+        let new = !self.contains(i);
+        self.0[i / 64] |= 1 << (i % 64);
+        new
     }
 }
 
-type Blizzards = [HashSet<P>; 4];
-
-#[derive(Debug)]
-struct Map {
-    blizzards: Blizzards,
+/// The valley with the blizzard occupancy of every minute within one period
+///
+/// Positions are relative to the inner top-left corner, so the entrance is at
+/// `y == -1` and the exit at `y == bounds.y`.
+pub struct Map {
     bounds: P,
+    /// Blizzards repeat after lcm(width, height) minutes
+    period: usize,
+    occupied: Vec<BitSet>,
 }
 
-const ZERO: P = P { x: 0, y: 0 };
-const N: P = P { x: 0, y: -1 };
-const E: P = P { x: 1, y: 0 };
-const S: P = P { x: 0, y: 1 };
-const W: P = P { x: -1, y: 0 };
-
 impl Map {
-    /// We do not include the real starting point in the simulation.
-    /// We start simulationg at one below the real starting point and end
-    /// one above the real end point. The `p_init` is inserted in each timestep,
-    /// to take into account, that it's allowed to wait on the real staring point.
-    fn count_ticks(&self, State { p: p_init, mut t }: State, target: P) -> Result<usize, &str> {
-        let mut states = HashSet::<State>::new();
-        let mut states_hist = HashSet::new();
-        for round in 1..1000 {
-            t.tick(&self.bounds);
-            states_hist.extend(states.clone());
+    pub fn entrance(&self) -> P {
+        P { x: 0, y: -1 }
+    }
+
+    pub fn exit(&self) -> P {
+        P {
+            x: self.bounds.x - 1,
+            y: self.bounds.y,
+        }
+    }
+
+    /// Index into the cells, with the entrance and exit appended after the
+    /// inner cells
+    fn idx(&self, p: &P) -> Option<usize> {
+        let n_inner = (self.bounds.x * self.bounds.y) as usize;
+        if *p == self.entrance() {
+            Some(n_inner)
+        } else if *p == self.exit() {
+            Some(n_inner + 1)
+        } else if self.bounds.within_bbox(p) {
+            Some((p.y * self.bounds.x + p.x) as usize)
+        } else {
+            None
+        }
+    }
+
+    fn n_cells(&self) -> usize {
+        (self.bounds.x * self.bounds.y) as usize + 2
+    }
+
+    /// Whether cell `i` is free of blizzards at minute `t`
+    fn is_free(&self, i: usize, t: usize) -> bool {
+        i >= self.n_cells() - 2 || !self.occupied[t % self.period].contains(i)
+    }
+
+    /// Minutes needed to go from `from` to `to`, starting at minute `t0`
+    ///
+    /// Searches breadth first over (position, minute mod period), so it
+    /// terminates once no new state is reachable.
+    fn count_ticks(&self, from: P, to: P, t0: usize) -> Result<usize, &str> {
+        if self.idx(&to).is_none() {
+            return Err("Target outside of the valley");
+        }
+        let start = self.idx(&from).ok_or("Start outside of the valley")?;
+        if from == to {
+            return Ok(0);
+        }
+        let mut visited = vec![BitSet::new(self.n_cells()); self.period];
+        visited[t0 % self.period].insert(start);
+        let mut states = vec![from];
+        for t in t0 + 1.. {
+            let visited = &mut visited[t % self.period];
             states = states
                 .into_iter()
-                .flat_map(|s| self.next(State { p: s.p, t }))
-                .chain(once(State { p: p_init, t }))
-                .filter(|s| !states_hist.contains(s))
+                .flat_map(|p| [ZERO, N, E, S, W].map(|offs| p + offs))
+                .filter(|p| {
+                    self.idx(p)
+                        .is_some_and(|i| self.is_free(i, t) && visited.insert(i))
+                })
                 .collect();
-
-            if states.iter().any(|s| s.p == target) {
-                return Ok(round + 1); // +1 to make another step down to _real_ end point
+            if states.contains(&to) {
+                return Ok(t - t0);
             }
             if states.is_empty() {
                 return Err("No states left");
             }
         }
-        Err("Exceeded max iterations")
-    }
-
-    fn next(&self, state: State) -> impl Iterator<Item = State> + '_ {
-        [ZERO, N, E, S, W].into_iter().filter_map(move |offs| {
-            let next_state = State {
-                p: state.p + offs,
-                t: state.t,
-            };
-            if self.bounds.within_bbox(&next_state.p)
-                && self
-                    .hor_vert_fwd_bwd(&next_state)
-                    .into_iter()
-                    .zip(self.blizzards.iter())
-                    .all(|(p, b)| !b.contains(&p))
-            {
-                Some(next_state)
-            } else {
-                None
-            }
-        })
+        unreachable!()
     }
 
-    fn hor_vert_fwd_bwd(&self, State { p, t }: &State) -> [P; 4] {
-        [
-            P {
-                x: wrap(p.x - t.x, self.bounds.x),
-                y: p.y,
-            },
-            P {
-                x: (p.x + t.x) % self.bounds.x,
-                y: p.y,
-            },
-            P {
-                x: p.x,
-                y: wrap(p.y - t.y, self.bounds.y),
-            },
-            P {
-                x: p.x,
-                y: (p.y + t.y) % self.bounds.y,
-            },
-        ]
+    /// Minutes needed to visit all `waypoints` in order, starting at the first
+    pub fn travel(&self, waypoints: &[P]) -> Result<usize, &str> {
+        waypoints
+            .windows(2)
+            .try_fold(0, |t, w| self.count_ticks(w[0], w[1], t).map(|n| t + n))
     }
 }
 
@@ -129,8 +140,7 @@ pub mod part1 {
 
     pub fn solution(s: &str) -> usize {
         let map = parse(s);
-        map.count_ticks(State { p: ZERO, t: ZERO }, map.bounds + P { x: -1, y: -1 })
-            .unwrap()
+        map.travel(&[map.entrance(), map.exit()]).unwrap()
     }
 
     #[test]
@@ -148,35 +158,8 @@ pub mod part2 {
 
     pub fn solution(s: &str) -> usize {
         let map = parse(s);
-        let end_point = map.bounds + P { x: -1, y: -1 };
-        let mut n = map
-            .count_ticks(State { p: ZERO, t: ZERO }, end_point)
-            .unwrap();
-        n += map
-            .count_ticks(
-                State {
-                    p: end_point,
-                    t: P {
-                        x: n as i32 % map.bounds.x,
-                        y: n as i32 % map.bounds.y,
-                    },
-                },
-                ZERO,
-            )
-            .unwrap();
-        n += map
-            .count_ticks(
-                State {
-                    p: ZERO,
-                    t: P {
-                        x: n as i32 % map.bounds.x,
-                        y: n as i32 % map.bounds.y,
-                    },
-                },
-                end_point,
-            )
-            .unwrap();
-        n
+        map.travel(&[map.entrance(), map.exit(), map.entrance(), map.exit()])
+            .unwrap()
     }
 
     #[test]
@@ -187,10 +170,25 @@ pub mod part2 {
     fn actual() {
         assert_eq!(solution(INPUT), 899);
     }
+    #[test]
+    fn waypoints() {
+        let map = parse(SAMPLE);
+        assert_eq!(map.travel(&[map.entrance()]), Ok(0));
+        assert_eq!(map.travel(&[map.entrance(), map.entrance()]), Ok(0));
+        assert_eq!(
+            map.travel(&[map.entrance(), map.exit(), map.exit()]),
+            Ok(18)
+        );
+        assert_eq!(
+            map.travel(&[map.entrance(), map.exit(), map.entrance()]),
+            Ok(18 + 23)
+        );
+        assert!(map.travel(&[map.entrance(), P { x: 9, y: 9 }]).is_err());
+    }
 }
 
-fn parse(s: &str) -> Map {
-    let mut blizzards: Blizzards = std::array::from_fn(|_| HashSet::new());
+pub fn parse(s: &str) -> Map {
+    let mut blizzards = Vec::new();
     let mut bounds = P { x: 0, y: 0 };
     let mut lines = s.lines().skip(1).enumerate().peekable();
     while let Some((y, l)) = lines.next() {
@@ -204,24 +202,33 @@ fn parse(s: &str) -> Map {
             .filter(|b| *b != b'#')
             .enumerate()
             .for_each(|(x, b)| {
-                let x = x as i32;
+                let p = P { x: x as i32, y };
                 match b {
-                    b'>' => {
-                        blizzards[0].insert(P { x, y });
-                    }
-                    b'<' => {
-                        blizzards[1].insert(P { x, y });
-                    }
-                    b'v' => {
-                        blizzards[2].insert(P { x, y });
-                    }
-                    b'^' => {
-                        blizzards[3].insert(P { x, y });
-                    }
+                    b'>' => blizzards.push((p, E)),
+                    b'<' => blizzards.push((p, W)),
+                    b'v' => blizzards.push((p, S)),
+                    b'^' => blizzards.push((p, N)),
                     b'.' => (),
                     _ => panic!(),
                 }
             });
     }
-    Map { blizzards, bounds }
+    let (w, h) = (bounds.x as usize, bounds.y as usize);
+    let period = math::lcm(w, h);
+    let occupied = (0..period as i32)
+        .map(|t| {
+            let mut occupied = BitSet::new(w * h);
+            blizzards.iter().for_each(|(p, d)| {
+                let x = (p.x + d.x * t).rem_euclid(bounds.x);
+                let y = (p.y + d.y * t).rem_euclid(bounds.y);
+                occupied.insert((y * bounds.x + x) as usize);
+            });
+            occupied
+        })
+        .collect();
+    Map {
+        bounds,
+        period,
+        occupied,
+    }
 }
@@ -1,5 +1,141 @@
+use std::collections::VecDeque;
+
 pub const INPUT: &str = include_str!("input.txt");
 
+/// Aho–Corasick automaton, which finds all words of a dictionary in a single
+/// pass over a text
+struct Automaton {
+    /// Transition for every state and byte
+    next: Vec<[u32; 256]>,
+    /// Value and length of the longest word that ends in each state
+    output: Vec<Option<(u32, usize)>>,
+    max_len: usize,
+}
+
+impl Automaton {
+    fn new<'a>(words: impl IntoIterator<Item = (&'a [u8], u32)> + Clone) -> Self {
+        // Trie, where 0 means there is no edge yet
+        let mut next = vec![[0; 256]];
+        let mut output = vec![None];
+        let mut max_len = 0;
+        for (word, value) in words {
+            let mut state = 0;
+            for &b in word {
+                if next[state][b as usize] == 0 {
+                    next.push([0; 256]);
+                    output.push(None);
+                    next[state][b as usize] = next.len() as u32 - 1;
+                }
+                state = next[state][b as usize] as usize;
+            }
+            output[state] = Some((value, word.len()));
+            max_len = max_len.max(word.len());
+        }
+        // Breadth first, missing edges follow the failure link, which
+        // is the longest proper suffix that's also in the trie
+        let mut fail = vec![0; next.len()];
+        let mut queue: VecDeque<_> = next[0].iter().map(|&s| s as usize).collect();
+        queue.retain(|&s| s != 0);
+        while let Some(state) = queue.pop_front() {
+            if output[state].is_none() {
+                output[state] = output[fail[state]];
+            }
+            let fallbacks = next[fail[state]];
+            for (edge, fallback) in next[state].iter_mut().zip(fallbacks) {
+                let child = *edge as usize;
+                if child == 0 {
+                    *edge = fallback;
+                } else {
+                    fail[child] = fallback as usize;
+                    queue.push_back(child);
+                }
+            }
+        }
+        Automaton {
+            next,
+            output,
+            max_len,
+        }
+    }
+
+    /// Value of the word that starts first, preferring longer words
+    fn first(&self, text: impl Iterator<Item = u8>) -> Option<u32> {
+        let mut state = 0;
+        let mut best: Option<(usize, usize, u32)> = None;
+        for (i, b) in text.enumerate() {
+            // No word that ends from here on can start at or before the best one
+            if best.is_some_and(|(start, _, _)| i >= start + self.max_len) {
+                break;
+            }
+            state = self.next[state][b as usize] as usize;
+            if let Some((value, len)) = self.output[state] {
+                let start = i + 1 - len;
+                if best.is_none_or(|(s, l, _)| start < s || start == s && len > l) {
+                    best = Some((start, len, value));
+                }
+            }
+        }
+        best.map(|(_, _, value)| value)
+    }
+}
+
+/// Words that stand for digits, searched forwards for the first and
+/// backwards for the last digit of a line
+pub struct Dictionary {
+    forward: Automaton,
+    backward: Automaton,
+}
+
+impl Dictionary {
+    pub fn new(words: &[(&str, u32)]) -> Self {
+        let reversed: Vec<(Vec<u8>, u32)> = words
+            .iter()
+            .map(|(w, v)| (w.bytes().rev().collect(), *v))
+            .collect();
+        Dictionary {
+            forward: Automaton::new(words.iter().map(|(w, v)| (w.as_bytes(), *v))),
+            backward: Automaton::new(reversed.iter().map(|(w, v)| (w.as_slice(), *v))),
+        }
+    }
+
+    /// First and last digit as a two digit number
+    pub fn calibration_value(&self, line: &str) -> Option<u32> {
+        let first = self.forward.first(line.bytes())?;
+        let last = self.backward.first(line.bytes().rev())?;
+        Some(first * 10 + last)
+    }
+}
+
+#[test]
+fn dictionary() {
+    let german = Dictionary::new(&[
+        ("eins", 1),
+        ("zwei", 2),
+        ("drei", 3),
+        ("vier", 4),
+        ("fünf", 5),
+        ("sechs", 6),
+        ("sieben", 7),
+        ("acht", 8),
+        ("neun", 9),
+    ]);
+    assert_eq!(german.calibration_value("zweins"), Some(21));
+    assert_eq!(german.calibration_value("xfünfx"), Some(55));
+    assert_eq!(german.calibration_value("achtzehn"), Some(88));
+    assert_eq!(german.calibration_value("zwölf"), None);
+    // Words inside other words
+    let nested = Dictionary::new(&[("abcd", 1), ("bc", 2), ("cdx", 3)]);
+    assert_eq!(nested.calibration_value("abcd"), Some(11));
+    assert_eq!(nested.calibration_value("abcdx"), Some(13));
+    assert_eq!(nested.calibration_value("zbcd"), Some(22));
+    // Words starting at the same position, forwards, or ending at the same
+    // position, backwards
+    let prefixed = Dictionary::new(&[("ab", 1), ("abc", 2), ("bc", 3)]);
+    assert_eq!(prefixed.calibration_value("abc"), Some(22));
+    assert_eq!(prefixed.calibration_value("abcab"), Some(21));
+    assert_eq!(prefixed.calibration_value("bcabc"), Some(32));
+}
+
 pub mod part1 {
     pub const SAMPLE: &str = include_str!("sample_part1.txt");
     #[cfg(test)]
@@ -28,50 +164,33 @@ pub mod part2 {
     const NUMBERS: [&str; 9] = [
         "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
     ];
-    const DIGITS: [&str; 10] = ["", "1", "2", "3", "4", "5", "6", "7", "8", "9"];
+    const DIGITS: [&str; 9] = ["1", "2", "3", "4", "5", "6", "7", "8", "9"];
 
-    #[cfg(test)]
     use super::*;
-    fn position(s: &str, num: usize, num_str: &str) -> Option<(usize, usize)> {
-        match (s.find(DIGITS[num]), s.find(num_str)) {
-            (Some(p1), Some(p2)) => Some((num, std::cmp::min(p1, p2))),
-            (Some(p), None) | (None, Some(p)) => Some((num, p)),
-            _ => None,
-        }
-    }
-    fn rposition(s: &str, num: usize, num_str: &str) -> Option<(usize, usize)> {
-        match (
-            s.rfind(DIGITS[num]),
-            s.rfind(&num_str).map(|p| p + num_str.len() - 1),
-        ) {
-            (Some(p1), Some(p2)) => Some((num, std::cmp::max(p1, p2))),
-            (Some(p), None) | (None, Some(p)) => Some((num, p)),
-            _ => None,
-        }
-    }
-    fn get_calibration_value(s: &str) -> usize {
-        let first = (1..)
-            .zip(NUMBERS)
-            .filter_map(|(num, num_str)| position(s, num, num_str))
-            .min_by_key(|(_, p)| *p)
-            .unwrap()
-            .0;
-        let last = (1..)
-            .zip(NUMBERS)
-            .filter_map(|(num, num_str)| rposition(s, num, num_str))
-            .max_by_key(|(_, p)| *p)
-            .unwrap()
-            .0;
-        first * 10 + last
+
+    /// Digits and English number words
+    pub fn english() -> Dictionary {
+        let words: Vec<_> = NUMBERS
+            .into_iter()
+            .zip(1..)
+            .chain(DIGITS.into_iter().zip(1..))
+            .collect();
+        Dictionary::new(&words)
     }
 
-    pub fn solution(s: &str) -> usize {
-        s.lines().map(get_calibration_value).sum()
+    pub fn solution(s: &str) -> u32 {
+        let dictionary = english();
+        s.lines()
+            .map(|l| dictionary.calibration_value(l).unwrap())
+            .sum()
     }
 
     #[test]
     fn sample() {
         assert_eq!(solution(SAMPLE), 281);
+        assert_eq!(english().calibration_value("twone"), Some(21));
+        assert_eq!(english().calibration_value("eightwothree"), Some(83));
+        assert_eq!(english().calibration_value("oneight"), Some(18));
     }
     #[test]
     fn actual() {
@@ -1,44 +1,125 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
 pub const INPUT: &str = include_str!("input.txt");
 
 pub const SAMPLE: &str = include_str!("sample.txt");
 
-type Rgb = [usize; 3];
-
-fn parse_rgb(s: &str) -> Rgb {
-    let mut result = [0; 3];
-    s.trim().split(',').for_each(|ss| {
-        let (n, color) = ss.trim().split_once(' ').unwrap();
-        let n = n.parse().unwrap();
-        match color {
-            "red" => result[0] = n,
-            "green" => result[1] = n,
-            "blue" => result[2] = n,
-            _ => panic! {"Unexpected color `{color}`"},
+/// Number of cubes of each colour
+pub type Cubes<'a> = BTreeMap<&'a str, usize>;
+
+/// The colours of the puzzle
+pub const COLOURS: [&str; 3] = ["red", "green", "blue"];
+
+/// One handful of cubes shown from the bag
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Draw<'a> {
+    pub cubes: Cubes<'a>,
+}
+
+impl Draw<'_> {
+    /// Whether the bag holds at least as many cubes of every colour
+    pub fn fits(&self, bag: &Cubes) -> bool {
+        self.cubes
+            .iter()
+            .all(|(colour, &n)| n <= bag.get(colour).copied().unwrap_or(0))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Game<'a> {
+    pub id: usize,
+    pub draws: Vec<Draw<'a>>,
+}
+
+impl<'a> Game<'a> {
+    /// The first draw that needs more cubes than `bag` holds, with its index
+    pub fn impossible_draw(&self, bag: &Cubes) -> Option<(usize, &Draw<'a>)> {
+        self.draws.iter().enumerate().find(|(_, d)| !d.fits(bag))
+    }
+
+    pub fn is_possible(&self, bag: &Cubes) -> bool {
+        self.impossible_draw(bag).is_none()
+    }
+
+    /// The fewest cubes of each colour that make all draws possible
+    pub fn minimum_bag(&self) -> Cubes<'a> {
+        let mut bag = Cubes::new();
+        for (&colour, &n) in self.draws.iter().flat_map(|d| &d.cubes) {
+            let m = bag.entry(colour).or_default();
+            *m = (*m).max(n);
+        }
+        bag
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// The line doesn't follow `Game <id>: <n> <colour>, ...; ...`
+    Syntax(String),
+    /// A colour that isn't part of the palette
+    UnknownColour(String),
+    /// The same colour appears twice in one draw
+    DuplicateColour(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Syntax(s) => write!(f, "Unexpected `{s}`"),
+            ParseError::UnknownColour(c) => write!(f, "Unknown colour `{c}`"),
+            ParseError::DuplicateColour(c) => write!(f, "Colour `{c}` drawn twice"),
         }
-    });
-    result
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn parse_draw<'a>(s: &'a str, colours: &[&str]) -> Result<Draw<'a>, ParseError> {
+    let mut cubes = Cubes::new();
+    for ss in s.split(',') {
+        let syntax = || ParseError::Syntax(ss.trim().to_string());
+        let (n, colour) = ss.trim().split_once(' ').ok_or_else(syntax)?;
+        let n = n.parse().map_err(|_| syntax())?;
+        if !colours.contains(&colour) {
+            return Err(ParseError::UnknownColour(colour.to_string()));
+        }
+        if cubes.insert(colour, n).is_some() {
+            return Err(ParseError::DuplicateColour(colour.to_string()));
+        }
+    }
+    Ok(Draw { cubes })
 }
-fn parse(s: &str) -> Vec<Rgb> {
-    s.split_once(':')
-        .unwrap()
-        .1
+
+/// Parse one line of the record, accepting only the given `colours`
+pub fn parse_game<'a>(line: &'a str, colours: &[&str]) -> Result<Game<'a>, ParseError> {
+    let syntax = || ParseError::Syntax(line.to_string());
+    let (game, draws) = line.split_once(':').ok_or_else(syntax)?;
+    let id = game
+        .strip_prefix("Game ")
+        .and_then(|id| id.parse().ok())
+        .ok_or_else(syntax)?;
+    let draws = draws
         .split(';')
-        .map(parse_rgb)
-        .collect()
+        .map(|d| parse_draw(d, colours))
+        .collect::<Result<_, _>>()?;
+    Ok(Game { id, draws })
+}
+
+pub fn parse(s: &str) -> Result<Vec<Game<'_>>, ParseError> {
+    s.lines().map(|l| parse_game(l, &COLOURS)).collect()
 }
+
 pub mod part1 {
     use super::*;
 
     pub fn solution(s: &str) -> usize {
-        let bag: Rgb = [12, 13, 14];
-        s.lines()
-            .map(parse)
-            .zip(1..)
-            .filter(|(rgbs, _)| {
-                rgbs.iter()
-                    .all(|&rgb| rgb.into_iter().zip(bag).all(|(c, b)| c <= b))
-            })
-            .map(|(_, i)| i)
+        let bag = Cubes::from([("red", 12), ("green", 13), ("blue", 14)]);
+        parse(s)
+            .unwrap()
+            .iter()
+            .filter(|game| game.is_possible(&bag))
+            .map(|game| game.id)
             .sum()
     }
 
@@ -50,22 +131,31 @@ pub mod part1 {
     fn actual() {
         assert_eq!(solution(INPUT), 2685);
     }
+    #[test]
+    fn impossible_draw() {
+        let bag = Cubes::from([("red", 12), ("green", 13), ("blue", 14)]);
+        let games = parse(SAMPLE).unwrap();
+        let impossible: Vec<_> = games
+            .iter()
+            .filter_map(|g| Some((g.id, g.impossible_draw(&bag)?.0)))
+            .collect();
+        assert_eq!(impossible, [(3, 0), (4, 2)]);
+        let (_, draw) = games[2].impossible_draw(&bag).unwrap();
+        assert_eq!(draw.cubes["red"], 20);
+    }
 }
 pub mod part2 {
     use super::*;
-    use std::cmp::max;
 
     pub fn solution(s: &str) -> usize {
-        s.lines()
-            .map(parse)
-            .map(|rgbs| {
-                rgbs.into_iter()
-                    .reduce(|acc, rgb| {
-                        let mut rgb = rgb.into_iter();
-                        acc.map(|a| max(a, rgb.next().unwrap()))
-                    })
-                    .unwrap()
-                    .into_iter()
+        parse(s)
+            .unwrap()
+            .iter()
+            .map(|game| {
+                let bag = game.minimum_bag();
+                COLOURS
+                    .iter()
+                    .map(|c| bag.get(c).copied().unwrap_or(0))
                     .product::<usize>()
             })
             .sum()
@@ -79,4 +169,31 @@ pub mod part2 {
     fn actual() {
         assert_eq!(solution(INPUT), 83707);
     }
+    #[test]
+    fn colours() {
+        let line = "Game 7: 2 cyan, 1 red; 4 magenta";
+        assert_eq!(
+            parse_game(line, &COLOURS),
+            Err(ParseError::UnknownColour("cyan".to_string()))
+        );
+        let game = parse_game(line, &["cyan", "magenta", "red"]).unwrap();
+        assert_eq!(game.id, 7);
+        assert_eq!(
+            game.minimum_bag(),
+            Cubes::from([("cyan", 2), ("magenta", 4), ("red", 1)])
+        );
+        assert!(!game.is_possible(&Cubes::from([("cyan", 2), ("red", 1)])));
+        assert_eq!(
+            parse_game("Game 1: 1 red, 2 red", &COLOURS),
+            Err(ParseError::DuplicateColour("red".to_string()))
+        );
+        assert!(matches!(
+            parse_game("Game x: 1 red", &COLOURS),
+            Err(ParseError::Syntax(_))
+        ));
+        assert!(matches!(
+            parse_game("Game 1: red", &COLOURS),
+            Err(ParseError::Syntax(_))
+        ));
+    }
 }
@@ -1,3 +1,8 @@
+use crate::checked::{self, Overflow};
+use crate::math;
+#[cfg(test)]
+use itertools::Itertools;
+
 pub const INPUT: &str = include_str!("input.txt");
 
 pub const SAMPLE: &str = include_str!("sample.txt");
@@ -12,11 +17,6 @@ struct Machine {
     y_targ: i64,
 }
 
-/// Divides `q` by `d` if `q` is exactly divisible by `d`
-fn if_even_checked_div(q: i64, d: i64) -> Option<Option<i64>> {
-    (q % d == 0).then_some(q.checked_div(d))
-}
-
 fn parse(s: &str) -> impl Iterator<Item = Machine> + '_ {
     s.split("\n\n").map(|s| {
         let mut lines = s.lines();
@@ -53,7 +53,8 @@ fn parse(s: &str) -> impl Iterator<Item = Machine> + '_ {
 }
 
 impl Machine {
-    fn price(&self) -> Option<i64> {
+    /// Tokens to win the prize, if that's possible
+    fn price(&self) -> Result<Option<i64>, Overflow> {
         // In the more common case there can only be one solution
         // and the costs don't really matter:
         //
@@ -80,30 +81,130 @@ impl Machine {
             y_targ,
         } = *self;
 
+        use checked::{add, mul, sub};
+
         // Calculate (8)
-        let Some(n_a) = if_even_checked_div(y_targ * x_b - x_targ * y_b, y_a * x_b - x_a * y_b)?
-        else {
+        let numerator = sub(mul(y_targ, x_b)?, mul(x_targ, y_b)?)?;
+        let denominator = sub(mul(y_a, x_b)?, mul(x_a, y_b)?)?;
+        if denominator == 0 {
             // If the divider gets zero, it gets more tricky
             // It means the vectors a and b are parallel
             // and there can be more than one solution
             unimplemented!("{self:?}")
+        }
+        let Some(n_a) = math::exact_div(numerator, denominator) else {
+            return Ok(None);
         };
 
         // Calculate (3)
-        let n_b = if_even_checked_div(x_targ - n_a * x_a, x_b)?.unwrap();
+        let Some(n_b) = math::exact_div(sub(x_targ, mul(n_a, x_a)?)?, x_b) else {
+            return Ok(None);
+        };
+
+        assert!(add(mul(n_a, x_a)?, mul(n_b, x_b)?)? == x_targ);
+        assert!(add(mul(n_a, y_a)?, mul(n_b, y_b)?)? == y_targ);
+
+        Ok(Some(add(mul(3, n_a)?, n_b)?))
+    }
+}
+
+/// Sum of the prices of all winnable machines
+fn total(machines: impl Iterator<Item = Machine>) -> Result<i64, Overflow> {
+    machines
+        .map(|m| m.price())
+        .filter_map(Result::transpose)
+        .try_fold(0, |sum, price| checked::add(sum, price?))
+}
 
-        assert!(n_a * x_a + n_b * x_b == x_targ);
-        assert!(n_a * y_a + n_b * y_b == y_targ);
+#[test]
+fn limits() {
+    // Pressing each button once reaches the prize, but the products in (8)
+    // grow with the square of the distances
+    let max_exp = if checked::ENABLED { 63 } else { 32 };
+    for e in 1..max_exp {
+        let p = 1 << e;
+        let machine = Machine {
+            x_a: p,
+            y_a: 1,
+            x_b: 1,
+            y_b: p,
+            x_targ: p + 1,
+            y_targ: p + 1,
+        };
+        match e {
+            ..=31 => assert_eq!(machine.price(), Ok(Some(4))),
+            _ => assert_eq!(machine.price(), Err(Overflow(checked::Operation::Mul))),
+        }
+    }
+}
 
-        Some(3 * n_a + n_b)
+#[test]
+fn generated() {
+    // Between 14 and 62 bits, so that products end up on both sides of the
+    // limit. Like in the puzzle, buttons always move along both axes.
+    let values = || {
+        let widths = checked::generate(8);
+        checked::generate(7)
+            .zip(widths)
+            .map(|(v, w)| (v >> (2 + w % 48)) as i64 + 1)
+    };
+    let mut n_checked = 0;
+    for (((x_a, y_a), (x_b, y_b)), (n_a, n_b)) in values()
+        .tuples()
+        .tuples()
+        .zip(values().skip(1000).tuples())
+        .take(3000)
+    {
+        let [x_a, y_a, x_b, y_b, n_a, n_b] = [x_a, y_a, x_b, y_b, n_a, n_b].map(|v| v as i128);
+        if y_a * x_b == x_a * y_b {
+            continue;
+        }
+        let (x_targ, y_targ) = (n_a * x_a + n_b * x_b, n_a * y_a + n_b * y_b);
+        let (Ok(x), Ok(y)) = (i64::try_from(x_targ), i64::try_from(y_targ)) else {
+            continue;
+        };
+        let machine = Machine {
+            x_a: x_a as i64,
+            y_a: y_a as i64,
+            x_b: x_b as i64,
+            y_b: y_b as i64,
+            x_targ: x,
+            y_targ: y,
+        };
+        // Every intermediate result of `price`
+        let intermediates = [
+            y_targ * x_b,
+            x_targ * y_b,
+            y_targ * x_b - x_targ * y_b,
+            y_a * x_b,
+            x_a * y_b,
+            y_a * x_b - x_a * y_b,
+            n_a * x_a,
+            n_b * x_b,
+            n_a * y_a,
+            n_b * y_b,
+            3 * n_a,
+            3 * n_a + n_b,
+        ];
+        if intermediates.iter().all(|&v| i64::try_from(v).is_ok()) {
+            assert_eq!(
+                machine.price(),
+                Ok(Some((3 * n_a + n_b) as i64)),
+                "{machine:?}"
+            );
+            n_checked += 1;
+        } else if checked::ENABLED {
+            assert!(machine.price().is_err(), "{machine:?}");
+        }
     }
+    assert!(n_checked > 0);
 }
 
 pub mod part1 {
     use super::*;
 
     pub fn solution(s: &str) -> i64 {
-        parse(s).filter_map(|m| m.price()).sum()
+        total(parse(s)).unwrap()
     }
 
     #[test]
@@ -121,13 +222,18 @@ pub mod part2 {
     use super::*;
 
     pub fn solution(s: &str) -> i64 {
-        parse(s)
-            .filter_map(|mut m| {
-                m.x_targ += 10000000000000;
-                m.y_targ += 10000000000000;
-                m.price()
+        try_solution(s).unwrap()
+    }
+
+    pub fn try_solution(s: &str) -> Result<i64, Overflow> {
+        let machines: Result<Vec<_>, _> = parse(s)
+            .map(|mut m| {
+                m.x_targ = checked::add(m.x_targ, 10000000000000)?;
+                m.y_targ = checked::add(m.y_targ, 10000000000000)?;
+                Ok(m)
             })
-            .sum()
+            .collect();
+        total(machines?.into_iter())
     }
 
     #[test]
@@ -1,5 +1,6 @@
 use std::{
-    collections::{HashSet, VecDeque},
+    cmp::Reverse,
+    collections::{BinaryHeap, VecDeque},
     ops::{Index, IndexMut},
     str::FromStr,
 };
@@ -55,77 +56,176 @@ pub mod part2 {
     fn actual() {
         assert_eq!(solution(INPUT), 481);
     }
+
+    #[test]
+    fn routes() {
+        let routes = Map::from_str(INPUT).unwrap().cheapest_routes(3);
+        assert!(routes.iter().all(|r| r.score == 94436));
+    }
+}
+
+/// Cost of stepping forward and of rotating by 90 degrees
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Costs {
+    pub step: u32,
+    pub turn: u32,
+}
+
+impl Default for Costs {
+    fn default() -> Self {
+        Costs {
+            step: 1,
+            turn: 1000,
+        }
+    }
+}
+
+/// Path through the maze that doesn't visit any tile twice
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Route {
+    pub score: u32,
+    /// Tiles as `(x, y)` from start to end, with the facing when entering them
+    pub tiles: Vec<((usize, usize), Direction)>,
 }
 
 impl Map {
-    fn minimum_score(&self) -> u32 {
-        let scores = self.flood();
+    pub fn with_costs(mut self, costs: Costs) -> Self {
+        self.costs = costs;
+        self
+    }
+
+    pub fn minimum_score(&self) -> u32 {
+        let scores = self.flood([self.start_state()], false);
         let end = self.find_singleton(Field::End);
         let score = scores[end.idx].minimum();
         assert!(score != u32::MAX, "Could not find a path");
         score
     }
 
-    fn count_tiles(&self) -> u32 {
-        let scores = self.flood();
+    /// Number of tiles that are part of at least one best path
+    pub fn count_tiles(&self) -> u32 {
+        let from_start = self.flood([self.start_state()], false);
+        let to_end = self.flood(self.end_states(), true);
+        let score = self.minimum_score();
+        (0..self.fields.len())
+            .filter(|&idx| {
+                ALL_DIRECTIONS
+                    .iter()
+                    .any(|&d| from_start[idx][d].saturating_add(to_end[idx][d]) == score)
+            })
+            .count()
+            .try_into()
+            .unwrap()
+    }
 
-        let mut queue = VecDeque::from_iter(self.all_end_states(&scores));
-        let mut seen = HashSet::new();
-        while let Some(State {
-            position,
-            direction,
-            score,
-        }) = queue.pop_front()
-        {
-            seen.insert(position.idx);
-            let new_states = [
-                (direction, score.checked_sub(1)),
-                (direction.left(), score.checked_sub(1000 + 1)),
-                (direction.right(), score.checked_sub(1000 + 1)),
-            ]
-            .into_iter()
-            .filter_map(|(direction, score)| {
-                let position = position.step(direction.reverse())?;
-                let score = score?;
-                (score == *scores[position.idx].index(direction)).then_some(State {
-                    position,
-                    direction,
-                    score,
-                })
-            });
-            queue.extend(new_states)
+    /// The `k` cheapest routes, ordered by score
+    ///
+    /// Best-first search over partial routes, guided by the exact remaining
+    /// cost from a backwards flood, so complete routes come out in order.
+    pub fn cheapest_routes(&self, k: usize) -> Vec<Route> {
+        let remaining = self.flood(self.end_states(), true);
+        let start = self.start_state();
+        let end = self.find_singleton(Field::End);
+        // Partial routes share their prefixes: (field, facing, predecessor)
+        let mut nodes = vec![(start.position.idx, start.direction, None)];
+        let estimate = remaining[start.position.idx][start.direction];
+        let mut heap = BinaryHeap::from([Reverse((estimate, 0, 0))]);
+        let mut routes = Vec::new();
+        while routes.len() < k {
+            let Some(Reverse((_, score, node))) = heap.pop() else {
+                break;
+            };
+            let (idx, facing, _) = nodes[node];
+            if idx == end.idx {
+                routes.push(self.route(&nodes, node, score));
+                continue;
+            }
+            let position = MapIndex { idx, map: self };
+            for direction in ALL_DIRECTIONS {
+                let Some(next) = position.step(direction) else {
+                    continue;
+                };
+                let estimate = remaining[next.idx][direction];
+                if estimate == u32::MAX || Map::visits(&nodes, node, next.idx) {
+                    continue;
+                }
+                let score = score + self.costs.step + self.costs.turn * facing.turns(direction);
+                nodes.push((next.idx, direction, Some(node)));
+                heap.push(Reverse((score + estimate, score, nodes.len() - 1)));
+            }
+        }
+        routes
+    }
+
+    fn visits(nodes: &[(usize, Direction, Option<usize>)], node: usize, idx: usize) -> bool {
+        std::iter::successors(Some(node), |&n| nodes[n].2).any(|n| nodes[n].0 == idx)
+    }
+
+    fn route(&self, nodes: &[(usize, Direction, Option<usize>)], node: usize, score: u32) -> Route {
+        let mut tiles: Vec<_> = std::iter::successors(Some(node), |&n| nodes[n].2)
+            .map(|n| {
+                (
+                    (nodes[n].0 % self.width, nodes[n].0 / self.width),
+                    nodes[n].1,
+                )
+            })
+            .collect();
+        tiles.reverse();
+        Route { score, tiles }
+    }
+
+    /// Draw the maze with arrows in the direction the routes leave each tile
+    pub fn render(&self, routes: &[Route]) -> String {
+        let mut chars: Vec<char> = self.fields.iter().map(|f| f.to_char()).collect();
+        for route in routes {
+            for (((x, y), _), (_, direction)) in route.tiles.iter().tuple_windows() {
+                let idx = y * self.width + x;
+                if self.fields[idx] == Field::Empty {
+                    chars[idx] = direction.arrow();
+                }
+            }
         }
-        seen.len().try_into().unwrap()
+        chars
+            .chunks(self.width)
+            .flat_map(|row| row.iter().chain(&['\n']))
+            .collect()
     }
 
-    fn flood(&self) -> Vec<Score> {
+    /// Cheapest score to reach every state, or to reach the end from it if
+    /// going `backwards` from the end states
+    fn flood<'a>(
+        &'a self,
+        starts: impl IntoIterator<Item = State<'a>>,
+        backwards: bool,
+    ) -> Vec<Score> {
         let mut scores = vec![Score::new(); self.fields.len()];
-        let start = State {
-            position: self.find_singleton(Field::Start),
-            direction: Direction::East,
-            score: 0,
-        };
-        scores[start.position.idx][start.direction] = 0;
-        let mut queue = VecDeque::from([start]);
+        let mut queue = VecDeque::new();
+        for start in starts {
+            scores[start.position.idx][start.direction] = start.score;
+            queue.push_back(start);
+        }
+        let Costs { step, turn } = self.costs;
         while let Some(State {
             position,
             direction,
             score,
         }) = queue.pop_front()
         {
+            let forward = match backwards {
+                false => direction,
+                true => direction.reverse(),
+            };
             let new_states = [
-                (position.step(direction), direction, score + 1),
-                (Some(position), direction.left(), score + 1000),
-                (Some(position), direction.right(), score + 1000),
-                // We don't have to look back, because it has already been visited
-                // and startpoint always has wall "behind" (west) it
+                (position.step(forward), direction, score + step),
+                (Some(position), direction.left(), score + turn),
+                (Some(position), direction.right(), score + turn),
             ]
             .into_iter()
             .filter_map(|(position, direction, score)| {
                 // Use this inner loop to eliminate states earlier for less allocations
                 let position = position?;
                 let stored_score = scores[position.idx].index_mut(direction);
-                (score <= *stored_score).then(|| {
+                (score < *stored_score).then(|| {
                     *stored_score = score;
                     State {
                         position,
@@ -139,26 +239,22 @@ impl Map {
         scores
     }
 
-    /// Get all end states that have the minimum score
-    fn all_end_states<'a>(&'a self, scores: &'a [Score]) -> impl Iterator<Item = State<'a>> {
-        const ALL_DIRECTIONS: [Direction; 4] = [
-            Direction::North,
-            Direction::East,
-            Direction::South,
-            Direction::West,
-        ];
+    fn start_state(&self) -> State<'_> {
+        State {
+            position: self.find_singleton(Field::Start),
+            direction: Direction::East,
+            score: 0,
+        }
+    }
+
+    /// The end with every facing
+    fn end_states(&self) -> impl Iterator<Item = State<'_>> {
         let end = self.find_singleton(Field::End);
-        let score = scores[end.idx].minimum();
-        assert!(score != u32::MAX, "Could not find a path");
-        scores[end.idx]
-            .0
-            .iter()
-            .positions(move |&v| v == score)
-            .map(move |i| State {
-                position: end,
-                direction: ALL_DIRECTIONS[i],
-                score,
-            })
+        ALL_DIRECTIONS.into_iter().map(move |direction| State {
+            position: end,
+            direction,
+            score: 0,
+        })
     }
 
     #[must_use]
@@ -172,6 +268,48 @@ impl Map {
     }
 }
 
+#[test]
+fn costs() {
+    let map = Map::from_str(SAMPLE1).unwrap();
+    let free_turns = Costs { step: 1, turn: 0 };
+    assert_eq!(map.with_costs(free_turns).minimum_score(), 28);
+}
+
+#[test]
+fn cheapest_routes() {
+    let map = Map::from_str(SAMPLE1).unwrap();
+    let routes = map.cheapest_routes(4);
+    let scores: Vec<_> = routes.iter().map(|r| r.score).collect();
+    assert_eq!(scores, [7036, 7036, 7036, 9040]);
+    assert!(routes.iter().map(|r| &r.tiles).all_unique());
+    let tiles: std::collections::HashSet<_> = routes[..3]
+        .iter()
+        .flat_map(|r| &r.tiles)
+        .map(|t| t.0)
+        .collect();
+    assert_eq!(tiles.len(), 45);
+    assert_eq!(
+        map.render(&routes[..1]),
+        concat!(
+            "###############\n",
+            "#.......#....E#\n",
+            "#.#.###.#.###^#\n",
+            "#.....#.#...#^#\n",
+            "#.###.#####.#^#\n",
+            "#.#.#.......#^#\n",
+            "#.#.#####.###^#\n",
+            "#....>>>>>>v#^#\n",
+            "###.#^#####v#^#\n",
+            "#...#^....#v#^#\n",
+            "#.#.#^###.#v#^#\n",
+            "#>>>>^#...#v#^#\n",
+            "#^###.#.#.#v#^#\n",
+            "#S..#.....#>>^#\n",
+            "###############\n",
+        )
+    );
+}
+
 struct State<'a> {
     position: MapIndex<'a>,
     direction: Direction,
@@ -206,8 +344,15 @@ impl IndexMut<Direction> for Score {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
-enum Direction {
+const ALL_DIRECTIONS: [Direction; 4] = [
+    Direction::North,
+    Direction::East,
+    Direction::South,
+    Direction::West,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
     North,
     East,
     South,
@@ -245,6 +390,24 @@ impl Direction {
     fn reverse(self) -> Self {
         self.left().left()
     }
+
+    /// Number of quarter turns to face `other`
+    fn turns(self, other: Self) -> u32 {
+        match (other.to_int() + 4 - self.to_int()) % 4 {
+            0 => 0,
+            2 => 2,
+            _ => 1,
+        }
+    }
+
+    fn arrow(self) -> char {
+        match self {
+            Direction::North => '^',
+            Direction::East => '>',
+            Direction::South => 'v',
+            Direction::West => '<',
+        }
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -291,9 +454,21 @@ enum Field {
     Empty,
 }
 
-struct Map {
+impl Field {
+    fn to_char(self) -> char {
+        match self {
+            Field::Wall => '#',
+            Field::Start => 'S',
+            Field::End => 'E',
+            Field::Empty => '.',
+        }
+    }
+}
+
+pub struct Map {
     fields: Vec<Field>,
     width: usize,
+    costs: Costs,
 }
 
 impl FromStr for Map {
@@ -314,6 +489,7 @@ impl FromStr for Map {
                 })
                 .collect(),
             width: s.find('\n').unwrap(),
+            costs: Costs::default(),
         })
     }
 }
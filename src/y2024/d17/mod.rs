@@ -10,7 +10,7 @@ pub mod part1 {
 
     pub fn solution(s: &str) -> String {
         let (mut state, program) = parse(s);
-        run(&mut state, program)
+        run(&mut state, &program)
     }
 
     #[test]
@@ -32,7 +32,7 @@ pub mod part2 {
     fn actual_proof() {
         let (mut state, program) = parse(INPUT);
         state.reg_a = 164540892147389;
-        assert_eq!(run(&mut state, program.clone()), program.0.iter().join(","));
+        assert_eq!(run(&mut state, &program), program.0.iter().join(","));
     }
 
     // I used code similar to the demo test below to find solutions for parts
@@ -69,11 +69,12 @@ pub mod part2 {
             let r = find_reg_a(Default::default(), parse(INPUT).1, s);
             println!("{:?} {r:b}", s);
         }
-        assert!(false);
+        panic!("fail to show the output");
     }
 
     fn find_reg_a(init_state: State, program: Program, expected: &[u8]) -> u64 {
-        'outer: for reg_a in 0.. {
+        let compiled = Compiled::new(&program);
+        for reg_a in 0.. {
             let mut state = State {
                 reg_a,
                 ..init_state
@@ -81,24 +82,24 @@ pub mod part2 {
             if reg_a % 10_000_000_000 == 0 {
                 dbg!(reg_a);
             }
-            let mut expected_sequence = expected.iter().peekable();
-            while let Some([instr, oper]) = program.0.get(state.ip..state.ip + 2) {
-                if let Some(output) = state.op(*instr, *oper) {
-                    if output == *expected_sequence.next().unwrap() {
-                        if expected_sequence.peek().is_none() {
-                            return reg_a;
-                        }
-                    } else {
-                        continue 'outer;
-                    }
+            let mut expected_sequence = expected.iter();
+            let mut complete = false;
+            compiled.run_with(&mut state, |output| {
+                if expected_sequence.next() != Some(&output) {
+                    return false;
                 }
+                complete = expected_sequence.len() == 0;
+                !complete
+            });
+            if complete {
+                return reg_a;
             }
         }
         unreachable!()
     }
 }
 
-fn run(state: &mut State, program: Program) -> String {
+pub fn run(state: &mut State, program: &Program) -> String {
     let mut out = Vec::new();
     while let Some([instr, oper]) = program.0.get(state.ip..state.ip + 2) {
         if let Some(v) = state.op(*instr, *oper) {
@@ -108,11 +109,11 @@ fn run(state: &mut State, program: Program) -> String {
     out.into_iter().join(",")
 }
 
-#[derive(Debug, Default)]
-struct State {
-    reg_a: u64,
-    reg_b: u64,
-    reg_c: u64,
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct State {
+    pub reg_a: u64,
+    pub reg_b: u64,
+    pub reg_c: u64,
     ip: usize,
 }
 
@@ -124,14 +125,14 @@ impl State {
             self.ip += 2;
         }
         match instr {
-            0 => self.reg_a /= 2_u64.pow(self.combo(operand).try_into().unwrap()),
+            0 => self.reg_a = shr(self.reg_a, self.combo(operand)),
             1 => self.reg_b ^= operand as u64,
             2 => self.reg_b = self.combo(operand) % 8,
             3 => (), // already handled above
             4 => self.reg_b ^= self.reg_c,
             5 => return Some((self.combo(operand) % 8) as u8),
-            6 => self.reg_b = self.reg_a / 2_u64.pow(self.combo(operand).try_into().unwrap()),
-            7 => self.reg_c = self.reg_a / 2_u64.pow(self.combo(operand).try_into().unwrap()),
+            6 => self.reg_b = shr(self.reg_a, self.combo(operand)),
+            7 => self.reg_c = shr(self.reg_a, self.combo(operand)),
             _ => panic!("Invalid instruction `{instr}`"),
         }
         None
@@ -149,9 +150,121 @@ impl State {
 }
 
 #[derive(Clone, Debug)]
-struct Program(Vec<u8>);
+pub struct Program(Vec<u8>);
+
+/// What a translated instruction tells the machine to do next
+enum Step {
+    Next,
+    Jump(usize),
+    Output(u8),
+}
+
+type Op = Box<dyn Fn(&mut State) -> Step>;
+
+/// Program translated into one closure per instruction pointer, with opcodes
+/// and operands decoded ahead of time
+///
+/// Every offset gets its own closure, so jumps to odd addresses behave like
+/// in [`State::op`]. Invalid instructions only panic when they're executed.
+pub struct Compiled(Vec<Op>);
+
+impl Compiled {
+    pub fn new(program: &Program) -> Self {
+        Compiled(
+            program
+                .0
+                .windows(2)
+                .map(|w| translate(w[0], w[1]))
+                .collect(),
+        )
+    }
+
+    /// Same output as [`run`]
+    pub fn run(&self, state: &mut State) -> String {
+        let mut out = Vec::new();
+        self.run_with(state, |v| {
+            out.push(v);
+            true
+        });
+        out.into_iter().join(",")
+    }
+
+    /// Run until the program halts or `output` returns `false`
+    fn run_with(&self, state: &mut State, mut output: impl FnMut(u8) -> bool) {
+        while let Some(op) = self.0.get(state.ip) {
+            match op(state) {
+                Step::Next => state.ip += 2,
+                Step::Jump(ip) => state.ip = ip,
+                Step::Output(v) => {
+                    state.ip += 2;
+                    if !output(v) {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// `value / 2^exp`, which is 0 once the divisor doesn't fit anymore
+fn shr(value: u64, exp: u64) -> u64 {
+    u32::try_from(exp)
+        .ok()
+        .and_then(|exp| value.checked_shr(exp))
+        .unwrap_or(0)
+}
 
-fn parse(s: &str) -> (State, Program) {
+fn translate(instr: u8, operand: u8) -> Op {
+    let literal = operand as u64;
+    match instr {
+        0 => with_combo(operand, |s, v| {
+            s.reg_a = shr(s.reg_a, v);
+            Step::Next
+        }),
+        1 => Box::new(move |s| {
+            s.reg_b ^= literal;
+            Step::Next
+        }),
+        2 => with_combo(operand, |s, v| {
+            s.reg_b = v % 8;
+            Step::Next
+        }),
+        3 => {
+            let target = operand as usize;
+            Box::new(move |s| match s.reg_a {
+                0 => Step::Next,
+                _ => Step::Jump(target),
+            })
+        }
+        4 => Box::new(|s| {
+            s.reg_b ^= s.reg_c;
+            Step::Next
+        }),
+        5 => with_combo(operand, |_, v| Step::Output((v % 8) as u8)),
+        6 => with_combo(operand, |s, v| {
+            s.reg_b = shr(s.reg_a, v);
+            Step::Next
+        }),
+        7 => with_combo(operand, |s, v| {
+            s.reg_c = shr(s.reg_a, v);
+            Step::Next
+        }),
+        _ => Box::new(move |_| panic!("Invalid instruction `{instr}`")),
+    }
+}
+
+/// Specialise `f` for the register or constant the combo operand refers to
+fn with_combo(operand: u8, f: impl Fn(&mut State, u64) -> Step + 'static) -> Op {
+    match operand {
+        o @ 0..=3 => Box::new(move |s| f(s, o as u64)),
+        4 => Box::new(move |s| f(s, s.reg_a)),
+        5 => Box::new(move |s| f(s, s.reg_b)),
+        6 => Box::new(move |s| f(s, s.reg_c)),
+        _ => Box::new(move |_| panic!("Invalid operand `{operand}`")),
+    }
+}
+
+pub fn parse(s: &str) -> (State, Program) {
     let mut lines = s.lines();
     (
         State {
@@ -186,7 +299,7 @@ fn parse(s: &str) -> (State, Program) {
                 .unwrap()
                 .split(',')
                 .map(|s| {
-                    assert!(s.as_bytes().len() == 1);
+                    assert!(s.len() == 1);
                     s.as_bytes()[0]
                         .checked_sub(48)
                         .expect("Op code should be a number")
@@ -228,7 +341,7 @@ mod test {
             ..Default::default()
         };
         assert_eq!(
-            run(&mut state, Program(vec![0, 1, 5, 4, 3, 0])),
+            run(&mut state, &Program(vec![0, 1, 5, 4, 3, 0])),
             "4,2,5,6,7,7,7,7,3,1,0"
         );
         assert_eq!(state.reg_a, 0);
@@ -244,6 +357,42 @@ mod test {
         assert_eq!(state.reg_b, 26);
     }
 
+    #[test]
+    fn compiled() {
+        for s in [SAMPLE, SAMPLE_PART2, INPUT] {
+            let (state, program) = parse(s);
+            let compiled = Compiled::new(&program);
+            for reg_a in (0..2000).chain([state.reg_a, 164540892147389]) {
+                let mut interpreted = State {
+                    reg_a,
+                    ..state.clone()
+                };
+                let mut translated = interpreted.clone();
+                assert_eq!(
+                    compiled.run(&mut translated),
+                    run(&mut interpreted, &program)
+                );
+                assert_eq!(translated, interpreted);
+            }
+        }
+        let mut state = State {
+            reg_a: 2024,
+            ..Default::default()
+        };
+        let compiled = Compiled::new(&Program(vec![0, 1, 5, 4, 3, 0]));
+        assert_eq!(compiled.run(&mut state), "4,2,5,6,7,7,7,7,3,1,0");
+        // Dividing by 2^100 doesn't overflow but gives 0
+        let program = Program(vec![0, 4, 5, 4]);
+        let mut state = State {
+            reg_a: 100,
+            ..Default::default()
+        };
+        let mut translated = state.clone();
+        assert_eq!(run(&mut state, &program), "0");
+        assert_eq!(Compiled::new(&program).run(&mut translated), "0");
+        assert_eq!(translated, state);
+    }
+
     #[test]
     fn four() {
         let mut state = State {
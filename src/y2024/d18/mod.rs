@@ -5,69 +5,216 @@ pub const INPUT: &str = include_str!("input.txt");
 
 pub const SAMPLE: &str = include_str!("sample.txt");
 
+/// Size of the memory space and number of bytes that fall for part 1
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Dimensions {
+    pub size: usize,
+    pub n_bytes: usize,
+}
+
+impl Dimensions {
+    /// Guess the dimensions of a puzzle input
+    ///
+    /// This is only a heuristic: the grid is assumed to end at the largest
+    /// coordinate of any byte, which is too small if no byte lands on the
+    /// last row or column. The number of bytes is the puzzle's 12 for grids
+    /// up to 7x7 like the example, and 1024 otherwise. Pass the dimensions to
+    /// the `solution_with` functions for any other input.
+    pub fn infer(s: &str) -> Dimensions {
+        let size = s
+            .lines()
+            .flat_map(|l| l.split(','))
+            .map(|v| usize::from_str(v).unwrap() + 1)
+            .max()
+            .unwrap_or(0);
+        let n_bytes = if size <= 7 { 12 } else { 1024 };
+        Dimensions { size, n_bytes }
+    }
+}
+
 pub mod part1 {
     use super::*;
 
-    pub fn solution<'a, const N: usize>(s: impl Iterator<Item = &'a str>) -> u32 {
-        Map::<N>::from_coords(s).flood()
+    pub fn solution(s: &str) -> u32 {
+        solution_with(s, Dimensions::infer(s))
+    }
+
+    pub fn solution_with(s: &str, Dimensions { size, n_bytes }: Dimensions) -> u32 {
+        Map::from_coords(size, s.lines().take(n_bytes)).flood()
     }
 
     #[test]
     fn sample() {
-        assert_eq!(solution::<7>(SAMPLE.lines().take(12)), 22);
+        assert_eq!(solution(SAMPLE), 22);
     }
 
     #[test]
     fn actual() {
-        assert_eq!(solution::<71>(INPUT.lines().take(1024)), 326);
+        assert_eq!(solution(INPUT), 326);
+    }
+
+    #[test]
+    fn explicit() {
+        let dims = Dimensions {
+            size: 71,
+            n_bytes: 1024,
+        };
+        assert_eq!(Dimensions::infer(INPUT), dims);
+        assert_eq!(
+            solution_with(
+                SAMPLE,
+                Dimensions {
+                    size: 7,
+                    n_bytes: 0
+                }
+            ),
+            12
+        );
     }
 }
 
 pub mod part2 {
     use super::*;
 
-    pub fn solution<const N: usize>(s: &str) -> &str {
-        Map::<N>::blocking_coord(s)
+    pub fn solution(s: &str) -> &str {
+        solution_with(s, Dimensions::infer(s).size)
+    }
+
+    pub fn solution_with(s: &str, size: usize) -> &str {
+        blocking(s, size).unwrap().coord
+    }
+
+    /// First byte after which the exit can't be reached anymore, or `None`
+    /// if it stays reachable
+    pub fn blocking(s: &str, size: usize) -> Option<Blocking<'_>> {
+        Map::blocking(s, size)
+    }
+
+    #[test]
+    fn last_path() {
+        let found = blocking(SAMPLE, 7).unwrap();
+        assert_eq!(found.step, 20);
+        assert_eq!(found.last_path.len(), 25);
+        assert_eq!(found.last_path.first(), Some(&(0, 0)));
+        assert_eq!(found.last_path.last(), Some(&(6, 6)));
+        assert!(found.last_path.contains(&(6, 1)));
+        assert!(blocking("1,1\n2,2", 7).is_none());
     }
 
     #[test]
     fn sample() {
-        assert_eq!(solution::<7>(SAMPLE), "6,1");
+        assert_eq!(solution(SAMPLE), "6,1");
+        // Without a byte in the last row or column the size can't be inferred
+        let s = "0,1\n1,1\n2,1\n3,1\n4,1\n4,0";
+        assert_eq!(Dimensions::infer(s).size, 5);
+        assert_eq!(solution_with(s, 7), "4,0");
     }
 
     #[test]
     // #[ignore = "slow"]
     fn actual() {
-        assert_eq!(solution::<71>(INPUT), "18,62");
+        assert_eq!(solution(INPUT), "18,62");
     }
 }
 
-impl<const N: usize> Map<N> {
-    fn blocking_coord(s: &str) -> &str {
-        let mut map = Map::<N>::default();
-        s.lines()
-            .find(|coord| {
-                map.put_block(coord);
-                map.flood() == u32::MAX
-            })
-            .unwrap()
+impl Map {
+    fn new(size: usize) -> Self {
+        Map {
+            size,
+            fields: vec![Field::Empty; size * size],
+        }
+    }
+
+    /// First byte after which the exit can't be reached anymore
+    ///
+    /// Starts with all bytes fallen and removes them in reverse order, while
+    /// tracking which free fields are connected in a [`UnionFind`].
+    fn blocking(s: &str, size: usize) -> Option<Blocking<'_>> {
+        let coords: Vec<&str> = s.lines().collect();
+        let mut map = Map::from_coords(size, coords.iter().cloned());
+        let mut n_bytes = vec![0; map.fields.len()];
+        coords.iter().for_each(|c| n_bytes[map.idx(c)] += 1);
+
+        let mut sets = UnionFind::new(map.fields.len());
+        (0..map.fields.len()).for_each(|idx| map.join_neighbors(&mut sets, idx));
+        let (start, end) = (map.start().idx, map.end().idx);
+        if sets.find(start) == sets.find(end) {
+            return None;
+        }
+        for (step, coord) in coords.iter().enumerate().rev() {
+            let idx = map.idx(coord);
+            n_bytes[idx] -= 1;
+            if n_bytes[idx] > 0 {
+                continue;
+            }
+            map.fields[idx] = Field::Empty;
+            map.join_neighbors(&mut sets, idx);
+            if sets.find(start) == sets.find(end) {
+                return Some(Blocking {
+                    step,
+                    coord,
+                    last_path: map.path().unwrap(),
+                });
+            }
+        }
+        None
+    }
+
+    fn join_neighbors(&self, sets: &mut UnionFind, idx: usize) {
+        let position = MapIndex { idx, map: self };
+        if position.field() == Field::Wall {
+            return;
+        }
+        DIRECTIONS
+            .into_iter()
+            .filter_map(|d| position.step(d))
+            .for_each(|n| sets.union(idx, n.idx));
     }
 
-    fn from_coords<'a>(s: impl Iterator<Item = &'a str>) -> Self {
-        let mut map = Map::default();
+    fn from_coords<'a>(size: usize, s: impl Iterator<Item = &'a str>) -> Self {
+        let mut map = Map::new(size);
         s.for_each(|coord| {
             map.put_block(coord);
         });
         map
     }
 
-    fn put_block(&mut self, coord: &str) {
+    fn idx(&self, coord: &str) -> usize {
         let (x, y) = coord.split_once(',').unwrap();
-        let idx = usize::from_str(x).unwrap() + usize::from_str(y).unwrap() * N;
+        usize::from_str(x).unwrap() + usize::from_str(y).unwrap() * self.size
+    }
+
+    fn put_block(&mut self, coord: &str) {
+        let idx = self.idx(coord);
         self.fields[idx] = Field::Wall
     }
 
     fn flood(&self) -> u32 {
+        self.distances()[self.end().idx].0
+    }
+
+    /// Shortest path from start to end as `(x, y)` coordinates
+    fn path(&self) -> Option<Vec<(usize, usize)>> {
+        let distances = self.distances();
+        let mut position = self.end();
+        let mut path = vec![position];
+        while distances[position.idx].0 > 0 {
+            let distance = distances[position.idx].0;
+            position = DIRECTIONS
+                .into_iter()
+                .filter_map(|d| position.step(d))
+                .find(|p| distances[p.idx].0 == distance - 1)?;
+            path.push(position);
+        }
+        Some(
+            path.into_iter()
+                .rev()
+                .map(|p| (p.idx % self.size, p.idx / self.size))
+                .collect(),
+        )
+    }
+
+    fn distances(&self) -> Vec<Distance> {
         let mut distances = vec![Distance::default(); self.fields.len()];
         let start = State {
             position: self.start(),
@@ -77,14 +224,7 @@ impl<const N: usize> Map<N> {
         let mut queue = VecDeque::from([start]);
         while let Some(State { position, distance }) = queue.pop_front() {
             // dbg!(position.idx, distance);
-            let new_states = [
-                Direction::North,
-                Direction::East,
-                Direction::South,
-                Direction::West,
-            ]
-            .into_iter()
-            .filter_map(|direction| {
+            let new_states = DIRECTIONS.into_iter().filter_map(|direction| {
                 let position = position.step(direction)?;
                 let distance = distance + 1;
                 let stored_distance = distances.index_mut(position.idx);
@@ -95,15 +235,15 @@ impl<const N: usize> Map<N> {
             });
             queue.extend(new_states)
         }
-        distances[self.end().idx].0
+        distances
     }
 
-    fn start(&self) -> MapIndex<'_, N> {
+    fn start(&self) -> MapIndex<'_> {
         MapIndex { idx: 0, map: self }
     }
-    fn end(&self) -> MapIndex<'_, N> {
+    fn end(&self) -> MapIndex<'_> {
         MapIndex {
-            idx: N * N - 1,
+            idx: self.fields.len() - 1,
             map: self,
         }
     }
@@ -118,8 +258,8 @@ impl Default for Distance {
     }
 }
 
-struct State<'a, const N: usize> {
-    position: MapIndex<'a, N>,
+struct State<'a> {
+    position: MapIndex<'a>,
     distance: u32,
 }
 
@@ -131,31 +271,71 @@ enum Direction {
     West,
 }
 
+const DIRECTIONS: [Direction; 4] = [
+    Direction::North,
+    Direction::East,
+    Direction::South,
+    Direction::West,
+];
+
+/// Result of the search for the first byte that cuts off the exit
+#[derive(Debug)]
+pub struct Blocking<'a> {
+    /// Index of the byte in the input
+    pub step: usize,
+    pub coord: &'a str,
+    /// A shortest path as `(x, y)` just before the byte falls
+    pub last_path: Vec<(usize, usize)>,
+}
+
+/// Disjoint sets of field indices
+struct UnionFind(Vec<usize>);
+
+impl UnionFind {
+    fn new(len: usize) -> Self {
+        UnionFind((0..len).collect())
+    }
+
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.0[i] != i {
+            self.0[i] = self.0[self.0[i]];
+            i = self.0[i];
+        }
+        i
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        self.0[a] = b;
+    }
+}
+
 #[derive(Clone, Copy)]
-struct MapIndex<'a, const N: usize> {
+struct MapIndex<'a> {
     idx: usize,
-    map: &'a Map<N>,
+    map: &'a Map,
 }
 
-impl<'a, const N: usize> MapIndex<'a, N> {
+impl<'a> MapIndex<'a> {
     fn field(&self) -> Field {
         self.map.fields[self.idx]
     }
 
     /// Return the index to the field in the desired direction if it's not a wall
-    fn step(self, direction: Direction) -> Option<MapIndex<'a, N>> {
+    fn step(self, direction: Direction) -> Option<MapIndex<'a>> {
+        let n = self.map.size;
         Some(Self {
             idx: match direction {
-                Direction::North => self.idx.checked_sub(N),
-                Direction::East => self.idx.checked_add(1).filter(|i| i.rem_euclid(N) != 0),
+                Direction::North => self.idx.checked_sub(n),
+                Direction::East => self.idx.checked_add(1).filter(|i| i.rem_euclid(n) != 0),
                 Direction::South => self
                     .idx
-                    .checked_add(N)
+                    .checked_add(n)
                     .filter(|i| *i < self.map.fields.len()),
                 Direction::West => self
                     .idx
                     .checked_sub(1)
-                    .filter(|_| self.idx.rem_euclid(N) != 0),
+                    .filter(|_| self.idx.rem_euclid(n) != 0),
             }?,
             map: self.map,
         })
@@ -169,14 +349,7 @@ enum Field {
     Empty,
 }
 
-struct Map<const N: usize> {
+struct Map {
+    size: usize,
     fields: Vec<Field>,
 }
-
-impl<const N: usize> Default for Map<N> {
-    fn default() -> Self {
-        Map {
-            fields: vec![Field::Empty; N * N],
-        }
-    }
-}